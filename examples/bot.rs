@@ -0,0 +1,63 @@
+//! Greedy bot playing a few seeded runs headless
+//!
+//! cargo run --example bot
+
+use bevy::prelude::IVec3;
+use rsnake3d::{grid_math, Direction, Rules, Simulation};
+
+const DIRECTIONS: [Direction; 6] = [
+    Direction::Up,
+    Direction::Down,
+    Direction::Left,
+    Direction::Right,
+    Direction::Forward,
+    Direction::Backward,
+];
+
+/// Ticks after which a run is cut short
+const MAX_TICKS: u64 = 1_000;
+
+/// Heads for the free neighbor closest to the food
+fn choose(sim: &Simulation) -> Option<Direction> {
+    let food = sim.food()?;
+    let body: Vec<IVec3> = sim.body().collect();
+    let rules = sim.rules();
+
+    DIRECTIONS
+        .into_iter()
+        .map(|direction| {
+            let cell = sim.head() + IVec3::from(direction);
+            if rules.wrap {
+                (
+                    direction,
+                    grid_math::wrap_to_bounds(cell, rules.half_extent),
+                )
+            } else {
+                (direction, cell)
+            }
+        })
+        .filter(|(_, cell)| {
+            grid_math::is_in_bounds(*cell, rules.half_extent)
+                && !body[1..].contains(cell)
+                && !sim.walls().contains(cell)
+        })
+        .min_by_key(|(_, cell)| grid_math::manhattan_distance(*cell, food))
+        .map(|(direction, _)| direction)
+}
+
+fn main() {
+    for seed in 0..5 {
+        let mut sim = Simulation::new(Rules::default(), seed);
+        while sim.tick() < MAX_TICKS && sim.ended().is_none() {
+            sim.step(choose(&sim));
+        }
+
+        println!(
+            "seed {seed}: {:?}, score {} after {} ticks, state {:016x}",
+            sim.ended(),
+            sim.score(),
+            sim.tick(),
+            sim.state_hash()
+        );
+    }
+}
@@ -0,0 +1,49 @@
+//! The game running inside a host Bevy app, next to the host's own systems
+//!
+//! cargo run --example embedded
+
+use bevy::prelude::*;
+use rsnake3d::prelude::*;
+
+fn main() {
+    App::new()
+        .add_plugins((
+            DefaultPlugins.set(WindowPlugin {
+                // close requests go through the game's quit confirmation
+                close_when_requested: false,
+                ..default()
+            }),
+            SnakeGamePlugin::default()
+                .with_grid(IVec3::splat(3))
+                .with_seed(7),
+        ))
+        .add_systems(Startup, skip_main_menu)
+        .add_systems(Update, (report_food, report_end, follow_head))
+        .run();
+}
+
+/// The host decides when a run starts, here right away
+fn skip_main_menu(mut next_state: ResMut<NextState<AppState>>) {
+    next_state.set(AppState::Playing);
+}
+
+fn report_food(mut eaten: EventReader<EatEvent>, score: Res<Score>) {
+    for EatEvent { kind } in eaten.read() {
+        println!("ate {kind:?}, score {}", score.0);
+    }
+}
+
+fn report_end(mut deaths: EventReader<DeathEvent>, mut wins: EventReader<GameWonEvent>) {
+    for DeathEvent { reason } in deaths.read() {
+        println!("run lost: {reason:?}");
+    }
+    for GameWonEvent { cause } in wins.read() {
+        println!("run won: {cause:?}");
+    }
+}
+
+fn follow_head(head: Query<(&Position, &SnakeHead), Changed<Position>>) {
+    for (Position(cell), SnakeHead(heading)) in head.iter() {
+        println!("host sees the head at {cell}, heading {heading:?}");
+    }
+}
@@ -0,0 +1,490 @@
+//! Device input turned into player actions
+//!
+//! Readers for the keyboard, gamepads, mouse and wheel fill [`ActionState`]
+//! every frame, and gameplay, camera and dialog systems only ever look at
+//! actions. Rebinding a button means editing [`InputBindings`].
+//!
+//! Readers run in a fixed order, mouse, gamepad, keyboard, so when devices
+//! turn in the same frame the keyboard's turn is the latest one, it wins
+//! under `LastWins` and is queued last under `Queue`.
+
+use bevy::{
+    input::{
+        gamepad::{GamepadConnection, GamepadConnectionEvent},
+        mouse::MouseWheel,
+        InputSystem,
+    },
+    prelude::*,
+    utils::HashMap,
+    window::PrimaryWindow,
+};
+
+use crate::{Direction, MainCamera};
+
+/// Stick deflection below which the stick counts as centered, so drift does
+/// not turn the snake
+const STICK_DEAD_ZONE: f32 = 0.5;
+
+/// What the player asked for during a frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlayerAction {
+    /// Turn the snake, already resolved against the camera
+    Turn(Direction),
+    /// Held, turns towards a food one axis away
+    AutoAlign,
+    /// Lines scrolled, positive zooms in
+    Zoom(f32),
+    /// Accepts the open dialog
+    Confirm,
+    /// Dismisses the open dialog
+    Cancel,
+    /// Starts a run, from the main menu or once the last one is over
+    Restart,
+    /// Pauses or resumes the run
+    Pause,
+    /// Moves the highlight of a menu, as seen on screen
+    Navigate(ScreenTurn),
+}
+
+/// A turn as seen on screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScreenTurn {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl ScreenTurn {
+    /// Screen space vector with y pointing up
+    fn vector(self) -> Vec2 {
+        match self {
+            ScreenTurn::Up => Vec2::Y,
+            ScreenTurn::Down => Vec2::NEG_Y,
+            ScreenTurn::Left => Vec2::NEG_X,
+            ScreenTurn::Right => Vec2::X,
+        }
+    }
+}
+
+/// What a button is bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    /// Turns relative to the camera
+    Turn(ScreenTurn),
+    /// Turns towards a fixed arena direction
+    Steer(Direction),
+    AutoAlign,
+    Confirm,
+    Cancel,
+    Restart,
+    Pause,
+    Navigate(ScreenTurn),
+}
+
+/// Device buttons and the actions they produce
+#[derive(Resource, Debug, Clone)]
+pub struct InputBindings {
+    pub keyboard: Vec<(KeyCode, Binding)>,
+    pub gamepad: Vec<(GamepadButtonType, Binding)>,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        Self {
+            keyboard: vec![
+                (KeyCode::Up, Binding::Turn(ScreenTurn::Up)),
+                (KeyCode::Down, Binding::Turn(ScreenTurn::Down)),
+                (KeyCode::Left, Binding::Turn(ScreenTurn::Left)),
+                (KeyCode::Right, Binding::Turn(ScreenTurn::Right)),
+                (KeyCode::W, Binding::Steer(Direction::Up)),
+                (KeyCode::S, Binding::Steer(Direction::Down)),
+                (KeyCode::A, Binding::Steer(Direction::Left)),
+                (KeyCode::D, Binding::Steer(Direction::Right)),
+                (KeyCode::Q, Binding::Steer(Direction::Forward)),
+                (KeyCode::E, Binding::Steer(Direction::Backward)),
+                (KeyCode::PageUp, Binding::Steer(Direction::Forward)),
+                (KeyCode::PageDown, Binding::Steer(Direction::Backward)),
+                (KeyCode::Space, Binding::AutoAlign),
+                (KeyCode::Y, Binding::Confirm),
+                (KeyCode::Return, Binding::Confirm),
+                (KeyCode::N, Binding::Cancel),
+                (KeyCode::R, Binding::Restart),
+                (KeyCode::Escape, Binding::Pause),
+                (KeyCode::P, Binding::Pause),
+                (KeyCode::Up, Binding::Navigate(ScreenTurn::Up)),
+                (KeyCode::Down, Binding::Navigate(ScreenTurn::Down)),
+                (KeyCode::W, Binding::Navigate(ScreenTurn::Up)),
+                (KeyCode::S, Binding::Navigate(ScreenTurn::Down)),
+            ],
+            gamepad: vec![
+                (GamepadButtonType::DPadUp, Binding::Turn(ScreenTurn::Up)),
+                (GamepadButtonType::DPadDown, Binding::Turn(ScreenTurn::Down)),
+                (GamepadButtonType::DPadLeft, Binding::Turn(ScreenTurn::Left)),
+                (
+                    GamepadButtonType::DPadRight,
+                    Binding::Turn(ScreenTurn::Right),
+                ),
+                (
+                    GamepadButtonType::LeftTrigger,
+                    Binding::Steer(Direction::Forward),
+                ),
+                (
+                    GamepadButtonType::RightTrigger,
+                    Binding::Steer(Direction::Backward),
+                ),
+                (GamepadButtonType::West, Binding::AutoAlign),
+                (GamepadButtonType::South, Binding::Confirm),
+                (GamepadButtonType::East, Binding::Cancel),
+                (GamepadButtonType::Select, Binding::Restart),
+                (GamepadButtonType::Start, Binding::Pause),
+                (GamepadButtonType::DPadUp, Binding::Navigate(ScreenTurn::Up)),
+                (
+                    GamepadButtonType::DPadDown,
+                    Binding::Navigate(ScreenTurn::Down),
+                ),
+            ],
+        }
+    }
+}
+
+/// Actions of the current frame, in the order they were read
+#[derive(Resource, Debug, Default, Clone)]
+pub struct ActionState {
+    actions: Vec<PlayerAction>,
+}
+
+impl ActionState {
+    pub fn push(&mut self, action: PlayerAction) {
+        self.actions.push(action);
+    }
+
+    /// Whether `action` happened this frame
+    pub fn pressed(&self, action: PlayerAction) -> bool {
+        self.actions.contains(&action)
+    }
+
+    /// Turns of this frame, oldest first
+    pub fn turns(&self) -> impl Iterator<Item = Direction> + '_ {
+        self.actions.iter().filter_map(|action| match action {
+            PlayerAction::Turn(direction) => Some(*direction),
+            _ => None,
+        })
+    }
+
+    /// Lines scrolled this frame
+    pub fn zoom(&self) -> f32 {
+        self.actions
+            .iter()
+            .map(|action| match action {
+                PlayerAction::Zoom(lines) => *lines,
+                _ => 0.0,
+            })
+            .sum()
+    }
+
+    /// Turns `binding` into an action, `None` for turns the camera cannot resolve
+    fn resolve(binding: Binding, camera_orientation: Quat) -> Option<PlayerAction> {
+        match binding {
+            Binding::Turn(turn) => {
+                Direction::from_screen(turn.vector(), camera_orientation).map(PlayerAction::Turn)
+            }
+            Binding::Steer(direction) => Some(PlayerAction::Turn(direction)),
+            Binding::AutoAlign => Some(PlayerAction::AutoAlign),
+            Binding::Confirm => Some(PlayerAction::Confirm),
+            Binding::Cancel => Some(PlayerAction::Cancel),
+            Binding::Restart => Some(PlayerAction::Restart),
+            Binding::Pause => Some(PlayerAction::Pause),
+            Binding::Navigate(turn) => Some(PlayerAction::Navigate(turn)),
+        }
+    }
+
+    /// Records a bound button, held bindings count every frame they are down
+    fn press(&mut self, binding: Binding, just_pressed: bool, pressed: bool, camera: Quat) {
+        let active = match binding {
+            Binding::AutoAlign => pressed,
+            _ => just_pressed,
+        };
+        if !active {
+            return;
+        }
+        if let Some(action) = Self::resolve(binding, camera) {
+            self.push(action);
+        }
+    }
+}
+
+/// Readers filling [`ActionState`], actions are ready once it has run
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ActionSet;
+
+pub struct ActionsPlugin;
+
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputBindings>()
+            .init_resource::<ActionState>()
+            .add_systems(
+                PreUpdate,
+                (
+                    clear_actions,
+                    read_mouse,
+                    read_gamepads,
+                    read_gamepad_sticks,
+                    read_keyboard,
+                )
+                    .chain()
+                    .in_set(ActionSet)
+                    .after(InputSystem),
+            );
+    }
+}
+
+fn clear_actions(mut actions: ResMut<ActionState>) {
+    actions.actions.clear();
+}
+
+/// Orientation of the main camera, turns are resolved relative to it
+fn camera_orientation(camera: &Query<&GlobalTransform, With<MainCamera>>) -> Quat {
+    camera
+        .get_single()
+        .map(|transform| transform.to_scale_rotation_translation().1)
+        .unwrap_or_default()
+}
+
+/// Turns by dragging across the viewport and zooms with the wheel
+fn read_mouse(
+    buttons: Res<Input<MouseButton>>,
+    mut wheel: EventReader<MouseWheel>,
+    primary_window_q: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<&GlobalTransform, With<MainCamera>>,
+    mut actions: ResMut<ActionState>,
+    mut drag_start: Local<Option<Vec2>>,
+) {
+    let lines: f32 = wheel.read().map(|event| event.y.signum()).sum();
+    if lines != 0.0 {
+        actions.push(PlayerAction::Zoom(lines));
+    }
+
+    if !buttons.pressed(MouseButton::Left) {
+        *drag_start = None;
+        return;
+    }
+
+    let Ok(window) = primary_window_q.get_single() else {
+        return;
+    };
+
+    let Some(cursor) = window.cursor_position() else {
+        return;
+    };
+
+    let Some(start) = *drag_start else {
+        *drag_start = Some(cursor);
+        return;
+    };
+
+    // window coordinates grow downwards, flip them to match the camera up axis
+    let delta = (cursor - start) * Vec2::new(1.0, -1.0);
+    let Some(direction) = Direction::from_mouse_delta(delta, camera_orientation(&camera)) else {
+        return;
+    };
+
+    // restart the drag so a long drag can chain several turns
+    *drag_start = Some(cursor);
+    actions.push(PlayerAction::Turn(direction));
+}
+
+fn read_gamepads(
+    gamepads: Res<Gamepads>,
+    buttons: Res<Input<GamepadButton>>,
+    bindings: Res<InputBindings>,
+    camera: Query<&GlobalTransform, With<MainCamera>>,
+    mut actions: ResMut<ActionState>,
+) {
+    let orientation = camera_orientation(&camera);
+    for gamepad in gamepads.iter() {
+        for (button_type, binding) in &bindings.gamepad {
+            let button = GamepadButton::new(gamepad, *button_type);
+            actions.press(
+                *binding,
+                buttons.just_pressed(button),
+                buttons.pressed(button),
+                orientation,
+            );
+        }
+    }
+}
+
+/// Steers with the left stick along the arena's up, down, left and right,
+/// turning once each time the stick is pushed towards a new direction
+fn read_gamepad_sticks(
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    mut connections: EventReader<GamepadConnectionEvent>,
+    mut actions: ResMut<ActionState>,
+    mut pushed: Local<HashMap<Gamepad, Direction>>,
+) {
+    for event in connections.read() {
+        if matches!(event.connection, GamepadConnection::Disconnected) {
+            pushed.remove(&event.gamepad);
+        }
+    }
+
+    for gamepad in gamepads.iter() {
+        let axis = |axis_type| {
+            axes.get(GamepadAxis::new(gamepad, axis_type))
+                .unwrap_or_default()
+        };
+        let stick = Vec2::new(
+            axis(GamepadAxisType::LeftStickX),
+            axis(GamepadAxisType::LeftStickY),
+        );
+
+        if stick.length() < STICK_DEAD_ZONE {
+            pushed.remove(&gamepad);
+            continue;
+        }
+
+        let direction = if stick.x.abs() > stick.y.abs() {
+            if stick.x > 0.0 {
+                Direction::Right
+            } else {
+                Direction::Left
+            }
+        } else if stick.y > 0.0 {
+            Direction::Up
+        } else {
+            Direction::Down
+        };
+
+        if pushed.insert(gamepad, direction) != Some(direction) {
+            actions.push(PlayerAction::Turn(direction));
+        }
+    }
+}
+
+fn read_keyboard(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<InputBindings>,
+    camera: Query<&GlobalTransform, With<MainCamera>>,
+    mut actions: ResMut<ActionState>,
+) {
+    let orientation = camera_orientation(&camera);
+    for (key, binding) in &bindings.keyboard {
+        actions.press(
+            *binding,
+            keys.just_pressed(*key),
+            keys.pressed(*key),
+            orientation,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::turns::{TurnBuffer, TurnPolicy};
+    use bevy::{
+        ecs::system::RunSystemOnce,
+        input::gamepad::{gamepad_connection_system, GamepadInfo},
+    };
+
+    use super::*;
+
+    const GAMEPAD: Gamepad = Gamepad { id: 0 };
+
+    /// App with the readers, the input resources they read and one gamepad
+    /// connected, without a camera so turns resolve as seen from the front
+    fn input_app() -> App {
+        let mut app = App::new();
+        app.init_resource::<Input<KeyCode>>()
+            .init_resource::<Input<MouseButton>>()
+            .init_resource::<Input<GamepadButton>>()
+            .init_resource::<Axis<GamepadAxis>>()
+            .init_resource::<Axis<GamepadButton>>()
+            .init_resource::<Gamepads>()
+            .add_event::<MouseWheel>()
+            .add_event::<GamepadConnectionEvent>()
+            .add_plugins(ActionsPlugin);
+
+        app.world.send_event(GamepadConnectionEvent::new(
+            GAMEPAD,
+            GamepadConnection::Connected(GamepadInfo {
+                name: "Test pad".into(),
+            }),
+        ));
+        app.world.run_system_once(gamepad_connection_system);
+        app
+    }
+
+    /// Presses `key` and `button` in the same frame and reads the actions
+    fn press_both(key: KeyCode, button: GamepadButtonType) -> ActionState {
+        let mut app = input_app();
+        app.world.resource_mut::<Input<KeyCode>>().press(key);
+        app.world
+            .resource_mut::<Input<GamepadButton>>()
+            .press(GamepadButton::new(GAMEPAD, button));
+        app.world.run_schedule(PreUpdate);
+        app.world.resource::<ActionState>().clone()
+    }
+
+    #[test]
+    fn keyboard_turns_come_after_gamepad_turns() {
+        let actions = press_both(KeyCode::Left, GamepadButtonType::DPadUp);
+        assert_eq!(
+            actions.turns().collect::<Vec<_>>(),
+            [Direction::Up, Direction::Left]
+        );
+
+        let mut last_wins = TurnBuffer::default();
+        let mut queue = TurnBuffer::default();
+        for turn in actions.turns() {
+            last_wins.request(TurnPolicy::LastWins, Direction::Forward, turn);
+            queue.request(TurnPolicy::Queue, Direction::Forward, turn);
+        }
+        assert_eq!(last_wins.next(Direction::Forward), Direction::Left);
+        assert_eq!(queue.next(Direction::Forward), Direction::Up);
+        assert_eq!(queue.latest(Direction::Forward), Direction::Left);
+    }
+
+    #[test]
+    fn both_devices_reach_the_same_actions() {
+        let actions = press_both(KeyCode::Return, GamepadButtonType::East);
+        assert!(actions.pressed(PlayerAction::Confirm));
+        assert!(actions.pressed(PlayerAction::Cancel));
+    }
+
+    #[test]
+    fn space_auto_aligns_without_restarting() {
+        let mut app = input_app();
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .press(KeyCode::Space);
+        app.world.run_schedule(PreUpdate);
+
+        let actions = app.world.resource::<ActionState>();
+        assert!(actions.pressed(PlayerAction::AutoAlign));
+        assert!(!actions.pressed(PlayerAction::Restart));
+    }
+
+    #[test]
+    fn no_button_both_restarts_and_pauses() {
+        let bindings = InputBindings::default();
+        for (key, binding) in &bindings.keyboard {
+            if *binding == Binding::Restart {
+                assert!(
+                    !bindings.keyboard.contains(&(*key, Binding::Pause)),
+                    "{key:?} restarts and pauses"
+                );
+            }
+        }
+        for (button, binding) in &bindings.gamepad {
+            if *binding == Binding::Restart {
+                assert!(
+                    !bindings.gamepad.contains(&(*button, Binding::Pause)),
+                    "{button:?} restarts and pauses"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,395 @@
+//! Presentation aids that help reading the 3D board
+
+use std::time::Duration;
+
+use bevy::{
+    audio::{Pitch, PitchBundle},
+    prelude::*,
+};
+
+use crate::{
+    actions::{ActionState, PlayerAction},
+    locks::Locked,
+    position_translation,
+    turns::TurnBuffer,
+    Direction, Food, GridBounds, GridConfig, Position, ReducedMotion, Rules, SnakeHead,
+};
+
+/// Pulses per second of the imminent eat highlight
+const PULSE_FREQUENCY: f32 = 3.0;
+
+/// Extra scale the food reaches at the top of a pulse
+const PULSE_SCALE: f32 = 0.25;
+
+/// Whether the depth cues shading the arena in line with the head are shown
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub struct DepthCues(pub bool);
+
+impl Default for DepthCues {
+    fn default() -> Self {
+        DepthCues(true)
+    }
+}
+
+/// Whether holding the auto align action may turn the head towards a food one
+/// axis away, off by default
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub struct AutoAlign(pub bool);
+
+/// The single turn lining the head up with `food`, if the two already share
+/// two coordinates and the turn is neither a no-op nor a reversal
+pub fn align_turn(head: IVec3, food: IVec3, current: Direction) -> Option<Direction> {
+    let delta = food - head;
+    if delta.to_array().iter().filter(|c| **c != 0).count() != 1 {
+        return None;
+    }
+
+    let turn = Direction::from(delta.signum());
+    let reversal = IVec3::from(turn) == -IVec3::from(current);
+    (turn != current && !reversal).then_some(turn)
+}
+
+/// Frequency of the food ping when the food is on the head's layer
+const PING_BASE_FREQUENCY: f32 = 440.0;
+
+/// How long a single food ping sounds
+const PING_DURATION: Duration = Duration::from_millis(120);
+
+/// Settings of the experimental food ping
+///
+/// Every `interval` seconds a short tone plays whose pitch tells where the
+/// food is vertically: each layer the food sits above the head raises the
+/// tone by a semitone, each layer below lowers it by one, and a tone at the
+/// base pitch means the food is on the head's layer. The shift is capped at
+/// `pitch_range` semitones either way, so far away food stays in tune.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Resource)]
+pub struct FoodPing {
+    pub enabled: bool,
+    /// Seconds between two pings
+    pub interval: f32,
+    /// Largest shift from the base pitch, in semitones
+    pub pitch_range: u32,
+}
+
+impl Default for FoodPing {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: 1.5,
+            pitch_range: 12,
+        }
+    }
+}
+
+/// Pitch multiplier encoding the food height relative to the head
+pub fn ping_pitch(food_y: i32, head_y: i32, pitch_range: u32) -> f32 {
+    let range = pitch_range as i32;
+    let semitones = (food_y - head_y).clamp(-range, range);
+    2f32.powf(semitones as f32 / 12.0)
+}
+
+/// How far the head leans towards a turn waiting for the next tick, 12°
+const TILT_ANGLE: f32 = std::f32::consts::PI / 15.0;
+
+/// Tag for the arrow pointing at a pending turn when motion is reduced
+#[derive(Component, Debug, Default)]
+struct TurnArrow;
+
+/// Axis the head leans around when `queued` differs from the direction
+/// it `committed` to on the last tick, `None` when going straight or
+/// reversing, as there is nothing sensible to lean towards
+pub fn tilt_axis(committed: Direction, queued: Direction) -> Option<Vec3> {
+    Vec3::from(committed)
+        .cross(Vec3::from(queued))
+        .try_normalize()
+}
+
+/// Tag for the floor overlay lighting up under a food about to be eaten
+#[derive(Component, Debug, Default)]
+struct EatHighlight;
+
+pub struct AssistPlugin;
+
+impl Plugin for AssistPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<DepthCues>()
+            .init_resource::<DepthCues>()
+            .register_type::<AutoAlign>()
+            .init_resource::<AutoAlign>()
+            .register_type::<FoodPing>()
+            .init_resource::<FoodPing>()
+            .add_systems(PostStartup, (spawn_eat_highlight, spawn_turn_arrow))
+            .add_systems(
+                Update,
+                (
+                    highlight_imminent_eat.after(position_translation),
+                    play_food_ping,
+                    auto_align,
+                    tilt_head_towards_turn
+                        .after(position_translation)
+                        .after(crate::rotate_head)
+                        .before(crate::apply_turn_actions)
+                        .before(auto_align),
+                ),
+            );
+    }
+}
+
+/// Cell the head will enter on the next tick
+pub fn next_cell(head: &Position, direction: Direction, bounds: &GridBounds) -> IVec3 {
+    bounds.step(head.0, direction)
+}
+
+fn spawn_eat_highlight(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Plane::from_size(1.0))),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgba(1., 0.9, 0.5, 0.5),
+                emissive: Color::rgb_linear(1.2, 1.0, 0.4),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            }),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        EatHighlight,
+        Name::new("Eat Highlight"),
+    ));
+}
+
+/// Pulses the food the head is about to eat and lights up the floor under it
+fn highlight_imminent_eat(
+    time: Res<Time>,
+    grid: Res<GridConfig>,
+    bounds: Res<GridBounds>,
+    reduced_motion: Res<ReducedMotion>,
+    turns: Res<TurnBuffer>,
+    head: Query<(&Position, &SnakeHead)>,
+    mut food: Query<
+        (&Position, &mut Transform),
+        (With<Food>, Without<Locked>, Without<EatHighlight>),
+    >,
+    mut highlight: Query<(&mut Transform, &mut Visibility), (With<EatHighlight>, Without<Food>)>,
+) {
+    let Ok((mut highlight_transform, mut highlight_visibility)) = highlight.get_single_mut() else {
+        return;
+    };
+
+    let target = head
+        .get_single()
+        .ok()
+        .map(|(pos, SnakeHead(heading))| next_cell(pos, turns.next(*heading), &bounds));
+    let imminent = food.iter_mut().find(|(pos, _)| Some(pos.0) == target);
+
+    let Some((food_pos, mut food_transform)) = imminent else {
+        *highlight_visibility = Visibility::Hidden;
+        return;
+    };
+
+    if !reduced_motion.0 {
+        let pulse = (time.elapsed_seconds() * PULSE_FREQUENCY * std::f32::consts::TAU).sin();
+        food_transform.scale *= 1.0 + PULSE_SCALE * (pulse * 0.5 + 0.5);
+    }
+
+    // project the food down onto the floor tiles
+    let floor_cell = IVec3::new(food_pos.0.x, -bounds.half_extent.y, food_pos.0.z);
+    let mut transform = grid.cell_transform(floor_cell);
+    transform.translation.y -= grid.cell_size / 2.0 - 0.01;
+    *highlight_transform = transform;
+    *highlight_visibility = Visibility::Inherited;
+}
+
+/// Plays the food ping, pitched after the food height relative to the head
+fn play_food_ping(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<FoodPing>,
+    mut pitches: ResMut<Assets<Pitch>>,
+    head: Query<&Position, With<SnakeHead>>,
+    food: Query<&Position, (With<Food>, Without<Locked>)>,
+    mut timer: Local<Timer>,
+) {
+    if !settings.enabled {
+        return;
+    }
+
+    let interval = Duration::from_secs_f32(settings.interval.max(0.1));
+    if timer.duration() != interval {
+        *timer = Timer::new(interval, TimerMode::Repeating);
+    }
+    if !timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let (Ok(head), Ok(food)) = (head.get_single(), food.get_single()) else {
+        return;
+    };
+
+    let pitch = ping_pitch(food.0.y, head.0.y, settings.pitch_range);
+    commands.spawn((
+        PitchBundle {
+            source: pitches.add(Pitch::new(PING_BASE_FREQUENCY * pitch, PING_DURATION)),
+            settings: PlaybackSettings::DESPAWN,
+        },
+        Name::new("Food Ping"),
+    ));
+    trace!(target: "rsnake3d::play_food_ping", pitch);
+}
+
+/// Turns the head towards a food one axis away while the assist key is held
+fn auto_align(
+    settings: Res<AutoAlign>,
+    actions: Res<ActionState>,
+    rules: Res<Rules>,
+    mut turns: ResMut<TurnBuffer>,
+    head: Query<(&Position, &SnakeHead)>,
+    food: Query<&Position, (With<Food>, Without<Locked>)>,
+) {
+    if !settings.0 || !actions.pressed(PlayerAction::AutoAlign) {
+        return;
+    }
+
+    let (Ok((head_pos, SnakeHead(heading))), Ok(food_pos)) = (head.get_single(), food.get_single())
+    else {
+        return;
+    };
+
+    let Some(turn) = align_turn(head_pos.0, food_pos.0, turns.latest(*heading)) else {
+        return;
+    };
+    if turns.request(rules.turn_policy, *heading, turn) {
+        debug!(target: "rsnake3d::auto_align", ?turn);
+    }
+}
+
+fn spawn_turn_arrow(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Cube { size: 0.2 })),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgb(1., 0.9, 0.5),
+                unlit: true,
+                ..default()
+            }),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        TurnArrow,
+        Name::new("Turn Arrow"),
+    ));
+}
+
+/// Leans the head towards a turn that takes effect on the next tick and
+/// straightens it once the move commits, with reduced motion a small arrow
+/// next to the head points at the turn instead
+fn tilt_head_towards_turn(
+    grid: Res<GridConfig>,
+    reduced_motion: Res<ReducedMotion>,
+    turns: Res<TurnBuffer>,
+    mut head: Query<(&Position, &SnakeHead, &mut Transform), Without<TurnArrow>>,
+    mut arrow: Query<(&mut Transform, &mut Visibility), With<TurnArrow>>,
+) {
+    let Ok((position, SnakeHead(heading), mut head_transform)) = head.get_single_mut() else {
+        return;
+    };
+
+    let queued = turns.next(*heading);
+    let axis = tilt_axis(*heading, queued);
+
+    if let Some(axis) = axis.filter(|_| !reduced_motion.0) {
+        head_transform.rotation = Quat::from_axis_angle(axis, TILT_ANGLE) * head_transform.rotation;
+    }
+
+    let Ok((mut arrow_transform, mut arrow_visibility)) = arrow.get_single_mut() else {
+        return;
+    };
+    if axis.is_some() && reduced_motion.0 {
+        let offset = Vec3::from(queued) * 0.6 * grid.cell_size;
+        arrow_transform.translation = grid.cell_to_world(position.0) + offset;
+        arrow_transform.scale = Vec3::splat(grid.cell_size);
+        *arrow_visibility = Visibility::Inherited;
+    } else {
+        *arrow_visibility = Visibility::Hidden;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn align_turn_turns_towards_food_on_a_crossing_line() {
+        let head = IVec3::new(1, 2, 3);
+        assert_eq!(
+            align_turn(head, IVec3::new(1, 2, -4), Direction::Up),
+            Some(Direction::Backward)
+        );
+        assert_eq!(
+            align_turn(head, IVec3::new(5, 2, 3), Direction::Forward),
+            Some(Direction::Right)
+        );
+    }
+
+    #[test]
+    fn align_turn_keeps_going_when_already_aligned() {
+        let head = IVec3::new(1, 2, 3);
+        assert_eq!(align_turn(head, IVec3::new(1, 6, 3), Direction::Up), None);
+    }
+
+    #[test]
+    fn align_turn_never_reverses() {
+        let head = IVec3::new(1, 2, 3);
+        assert_eq!(align_turn(head, IVec3::new(1, -2, 3), Direction::Up), None);
+        assert_eq!(
+            align_turn(head, IVec3::new(0, 2, 3), Direction::Right),
+            None
+        );
+    }
+
+    #[test]
+    fn align_turn_ignores_food_off_the_axes() {
+        let head = IVec3::new(1, 2, 3);
+        assert_eq!(align_turn(head, IVec3::new(2, 3, 3), Direction::Up), None);
+        assert_eq!(align_turn(head, head, Direction::Up), None);
+    }
+
+    #[test]
+    fn turns_tilt_around_the_axis_between_them() {
+        assert_eq!(
+            tilt_axis(Direction::Up, Direction::Right),
+            Some(Vec3::NEG_Z)
+        );
+        assert_eq!(
+            tilt_axis(Direction::Forward, Direction::Left),
+            Some(Vec3::NEG_Y)
+        );
+    }
+
+    #[test]
+    fn going_straight_or_reversing_does_not_tilt() {
+        for direction in [
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+            Direction::Forward,
+            Direction::Backward,
+        ] {
+            assert_eq!(tilt_axis(direction, direction), None);
+            assert_eq!(tilt_axis(direction, direction.opposite()), None);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! Per-tick hash of the board, for telling where two runs diverge
+//!
+//! The hash is shown in the editor's resource inspector and F8 copies
+//! `tick <n> hash <hash>` to the clipboard while the debug overlay is on.
+//! The hash itself is [`simulation::board_hash`], so a headless
+//! `Simulation` fed the same inputs reports the same value.
+
+use bevy::prelude::*;
+use bevy_inspector_egui::bevy_egui::{EguiContexts, EguiSettings};
+
+use crate::{
+    hud::DebugOverlay, simulation, Food, Position, Score, SnakeHead, SnakeSegment, TickTimer,
+};
+
+/// Copies the current hash to the clipboard
+const COPY_KEY: KeyCode = KeyCode::F8;
+
+/// Hash of the board after the latest gameplay tick
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub struct BoardHash {
+    pub tick: u64,
+    pub hash: u64,
+}
+
+impl std::fmt::Display for BoardHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "tick {} hash {:016x}", self.tick, self.hash)
+    }
+}
+
+pub struct BoardHashPlugin;
+
+impl Plugin for BoardHashPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<BoardHash>()
+            .init_resource::<BoardHash>()
+            .add_systems(
+                FixedUpdate,
+                update_board_hash.in_set(crate::TickSet::Record),
+            )
+            // egui comes with the editor, a host app may not have it
+            .add_systems(
+                Update,
+                copy_board_hash.run_if(resource_exists::<EguiSettings>()),
+            );
+    }
+}
+
+fn update_board_hash(
+    tick: Res<TickTimer>,
+    score: Res<Score>,
+    head: Query<(&Position, &SnakeHead)>,
+    body: Query<&Position, (With<SnakeSegment>, Without<SnakeHead>)>,
+    food: Query<&Position, With<Food>>,
+    mut board_hash: ResMut<BoardHash>,
+) {
+    let head = head.get_single().ok().map(|(pos, dir)| (pos.0, dir.0));
+    let hash = simulation::board_hash(
+        head,
+        body.iter().map(|p| p.0),
+        food.iter().map(|p| p.0),
+        score.0,
+        tick.count,
+    );
+
+    *board_hash = BoardHash {
+        tick: tick.count,
+        hash,
+    };
+    trace!(target: "rsnake3d::update_board_hash", "{}", *board_hash);
+}
+
+fn copy_board_hash(
+    keys: Res<Input<KeyCode>>,
+    overlay: Res<DebugOverlay>,
+    board_hash: Res<BoardHash>,
+    mut contexts: EguiContexts,
+) {
+    if !overlay.0 || !keys.just_pressed(COPY_KEY) {
+        return;
+    }
+
+    let text = board_hash.to_string();
+    info!(target: "rsnake3d::copy_board_hash", "Copied {text}");
+    contexts
+        .ctx_mut()
+        .output_mut(|output| output.copied_text = text);
+}
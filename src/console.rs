@@ -0,0 +1,363 @@
+//! Debug console toggled with the backtick key, only in debug builds
+//!
+//! While open the console takes every key press, so typing does not steer
+//! the snake or toggle overlays. `help` lists the commands.
+
+use std::time::Duration;
+
+use bevy::{input::InputSystem, prelude::*, window::ReceivedCharacter};
+
+use crate::{
+    board_hash::BoardHash, grid_math, hud::UiAssets, walls::Wall, Food, FoodBundle, GameRng,
+    GridBounds, GridConfig, PendingGrowth, Position, SnakeAssets, SnakeHead, SnakeSegment,
+    TickTimer,
+};
+
+/// Opens and closes the console
+const TOGGLE_KEY: KeyCode = KeyCode::Grave;
+
+/// Lines of output kept on screen
+const HISTORY_LINES: usize = 8;
+
+/// Allowed values for `set tick`, in milliseconds
+const TICK_INTERVAL_MS: std::ops::RangeInclusive<u64> = 100..=5000;
+
+/// Every command with its usage line
+const COMMANDS: [(&str, &str); 7] = [
+    ("help", "help"),
+    ("spawn", "spawn food <x> <y> <z>"),
+    ("grow", "grow <segments>"),
+    ("set", "set tick <ms>"),
+    ("teleport", "teleport <x> <y> <z>"),
+    ("state", "state"),
+    ("seed", "seed"),
+];
+
+/// A parsed console line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleCommand {
+    Help,
+    SpawnFood(IVec3),
+    Grow(u32),
+    SetTick(Duration),
+    Teleport(IVec3),
+    State,
+    Seed,
+}
+
+/// Parses a console line, the error explains what was expected
+pub fn parse_command(line: &str) -> Result<ConsoleCommand, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    match words.as_slice() {
+        ["help"] => Ok(ConsoleCommand::Help),
+        ["spawn", "food", x, y, z] => parse_cell(x, y, z).map(ConsoleCommand::SpawnFood),
+        ["grow", segments] => match parse_number::<u32>(segments)? {
+            0 => Err("grow needs at least one segment".to_string()),
+            segments => Ok(ConsoleCommand::Grow(segments)),
+        },
+        ["set", "tick", ms] => {
+            let ms = parse_number::<u64>(ms)?;
+            if !TICK_INTERVAL_MS.contains(&ms) {
+                return Err(format!(
+                    "tick must be between {} and {} ms",
+                    TICK_INTERVAL_MS.start(),
+                    TICK_INTERVAL_MS.end()
+                ));
+            }
+            Ok(ConsoleCommand::SetTick(Duration::from_millis(ms)))
+        }
+        ["teleport", x, y, z] => parse_cell(x, y, z).map(ConsoleCommand::Teleport),
+        ["state"] => Ok(ConsoleCommand::State),
+        ["seed"] => Ok(ConsoleCommand::Seed),
+        [] => Err("type help for a list of commands".to_string()),
+        [name, ..] => match COMMANDS.iter().find(|(command, _)| command == name) {
+            Some((_, usage)) => Err(format!("usage: {usage}")),
+            None => Err(format!(
+                "unknown command `{name}`, type help for a list of commands"
+            )),
+        },
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(word: &str) -> Result<T, String> {
+    word.parse()
+        .map_err(|_| format!("`{word}` is not a valid number"))
+}
+
+fn parse_cell(x: &str, y: &str, z: &str) -> Result<IVec3, String> {
+    Ok(IVec3::new(
+        parse_number(x)?,
+        parse_number(y)?,
+        parse_number(z)?,
+    ))
+}
+
+/// State of the console, the line being typed and recent output
+#[derive(Resource, Debug, Default)]
+struct Console {
+    open: bool,
+    input: String,
+    history: Vec<String>,
+}
+
+impl Console {
+    fn print(&mut self, line: impl Into<String>) {
+        self.history.push(line.into());
+        if self.history.len() > HISTORY_LINES {
+            self.history.remove(0);
+        }
+    }
+}
+
+/// Tag for the console text
+#[derive(Component, Debug, Default)]
+struct ConsoleText;
+
+/// Sent when a line is submitted and parsed successfully
+#[derive(Event, Debug, Clone, Copy)]
+struct ConsoleCommandEvent(ConsoleCommand);
+
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Console>()
+            .add_event::<ConsoleCommandEvent>()
+            .add_systems(PostStartup, setup_console_text)
+            .add_systems(
+                PreUpdate,
+                console_input
+                    .after(InputSystem)
+                    .before(crate::actions::ActionSet),
+            )
+            .add_systems(Update, (run_console_commands, update_console_text).chain());
+    }
+}
+
+fn setup_console_text(mut commands: Commands, ui_assets: Res<UiAssets>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: ui_assets.font.clone(),
+                font_size: 16.0,
+                color: Color::rgb(0.8, 1.0, 0.8),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(8.0),
+            left: Val::Px(8.0),
+            ..default()
+        })
+        .with_background_color(Color::rgba(0.0, 0.0, 0.0, 0.7)),
+        ConsoleText,
+        Name::new("Console"),
+    ));
+}
+
+/// Types into the console and swallows the keyboard while it is open
+fn console_input(
+    mut console: ResMut<Console>,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut submitted: EventWriter<ConsoleCommandEvent>,
+) {
+    if keys.just_pressed(TOGGLE_KEY) {
+        console.open = !console.open;
+        characters.clear();
+        keys.reset_all();
+        return;
+    }
+
+    if !console.open {
+        characters.clear();
+        return;
+    }
+
+    for ReceivedCharacter { char, .. } in characters.read() {
+        if !char.is_control() && *char != '`' {
+            console.input.push(*char);
+        }
+    }
+
+    if keys.just_pressed(KeyCode::Back) {
+        console.input.pop();
+    }
+
+    if keys.just_pressed(KeyCode::Return) {
+        let line = std::mem::take(&mut console.input);
+        console.print(format!("> {line}"));
+        match parse_command(&line) {
+            Ok(command) => submitted.send(ConsoleCommandEvent(command)),
+            Err(err) => console.print(err),
+        }
+    }
+
+    keys.reset_all();
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn run_console_commands(
+    mut commands: Commands,
+    mut submitted: EventReader<ConsoleCommandEvent>,
+    mut console: ResMut<Console>,
+    mut tick: ResMut<TickTimer>,
+    mut pending: ResMut<PendingGrowth>,
+    mut head: Query<&mut Position, With<SnakeHead>>,
+    occupied: Query<
+        &Position,
+        (
+            Or<(With<SnakeSegment>, With<Food>, With<Wall>)>,
+            Without<SnakeHead>,
+        ),
+    >,
+    (grid, bounds, snake_assets, board_hash, rng): (
+        Res<GridConfig>,
+        Res<GridBounds>,
+        Res<SnakeAssets>,
+        Res<BoardHash>,
+        Res<GameRng>,
+    ),
+) {
+    for ConsoleCommandEvent(command) in submitted.read() {
+        debug!(target: "rsnake3d::run_console_commands", ?command);
+        match *command {
+            ConsoleCommand::Help => {
+                let usages: Vec<&str> = COMMANDS.iter().map(|(_, usage)| *usage).collect();
+                console.print(usages.join(", "));
+            }
+            ConsoleCommand::SpawnFood(cell) => {
+                let taken = head.iter().chain(occupied.iter()).any(|p| p.0 == cell);
+                if !grid_math::is_in_bounds(cell, bounds.half_extent) || taken {
+                    console.print(format!("{cell} is outside the arena or taken"));
+                    continue;
+                }
+
+                commands.spawn(FoodBundle {
+                    position: Position(cell),
+                    mesh: snake_assets.food_mesh.clone(),
+                    material: snake_assets.food_material.clone(),
+                    transform: grid.cell_transform(cell),
+                    ..default()
+                });
+                console.print(format!("spawned food at {cell}"));
+            }
+            ConsoleCommand::Grow(segments) => {
+                // grown one per tick like a golden food, so the body unfolds
+                pending.0 = pending.0.saturating_add(segments);
+                console.print(format!("growing by {segments}"));
+            }
+            ConsoleCommand::SetTick(interval) => {
+                tick.set_override(interval);
+                console.print(format!("tick set to {} ms", interval.as_millis()));
+            }
+            ConsoleCommand::Teleport(cell) => {
+                let Ok(mut head) = head.get_single_mut() else {
+                    console.print("there is no head to teleport");
+                    continue;
+                };
+                if !grid_math::is_in_bounds(cell, bounds.half_extent) {
+                    console.print(format!("{cell} is outside the arena"));
+                    continue;
+                }
+
+                head.0 = cell;
+                console.print(format!("teleported the head to {cell}"));
+            }
+            ConsoleCommand::State => console.print(format!(
+                "{}, interval {} ms",
+                *board_hash,
+                tick.interval.as_millis()
+            )),
+            ConsoleCommand::Seed => console.print(format!("seed {}", rng.seed())),
+        }
+    }
+}
+
+fn update_console_text(
+    console: Res<Console>,
+    mut text: Query<(&mut Text, &mut Visibility), With<ConsoleText>>,
+) {
+    if !console.is_changed() {
+        return;
+    }
+
+    let Ok((mut text, mut visibility)) = text.get_single_mut() else {
+        return;
+    };
+
+    *visibility = if console.open {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+
+    let mut lines = console.history.join("\n");
+    if !lines.is_empty() {
+        lines.push('\n');
+    }
+    lines.push_str(&format!("> {}_", console.input));
+    text.sections[0].value = lines;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_parse_with_any_spacing() {
+        assert_eq!(parse_command("help"), Ok(ConsoleCommand::Help));
+        assert_eq!(
+            parse_command("  spawn  food 1 -2 3 "),
+            Ok(ConsoleCommand::SpawnFood(IVec3::new(1, -2, 3)))
+        );
+        assert_eq!(parse_command("grow 4"), Ok(ConsoleCommand::Grow(4)));
+        assert_eq!(
+            parse_command("set tick 250"),
+            Ok(ConsoleCommand::SetTick(Duration::from_millis(250)))
+        );
+        assert_eq!(
+            parse_command("teleport 0 0 -1"),
+            Ok(ConsoleCommand::Teleport(IVec3::NEG_Z))
+        );
+        assert_eq!(parse_command("state"), Ok(ConsoleCommand::State));
+        assert_eq!(parse_command("seed"), Ok(ConsoleCommand::Seed));
+    }
+
+    #[test]
+    fn tick_interval_is_range_checked() {
+        assert!(parse_command("set tick 100").is_ok());
+        assert!(parse_command("set tick 5000").is_ok());
+        assert_eq!(
+            parse_command("set tick 99"),
+            Err("tick must be between 100 and 5000 ms".to_string())
+        );
+        assert!(parse_command("set tick 5001").is_err());
+        assert!(parse_command("set tick -5").is_err());
+    }
+
+    #[test]
+    fn bad_lines_explain_what_was_expected() {
+        assert_eq!(
+            parse_command(""),
+            Err("type help for a list of commands".to_string())
+        );
+        assert_eq!(
+            parse_command("grow"),
+            Err("usage: grow <segments>".to_string())
+        );
+        assert_eq!(
+            parse_command("grow 0"),
+            Err("grow needs at least one segment".to_string())
+        );
+        assert_eq!(
+            parse_command("teleport 1 two 3"),
+            Err("`two` is not a valid number".to_string())
+        );
+        assert_eq!(
+            parse_command("fly"),
+            Err("unknown command `fly`, type help for a list of commands".to_string())
+        );
+    }
+}
@@ -0,0 +1,172 @@
+//! Rolling record of the last seconds of a run, dumped when the run ends
+//!
+//! Every gameplay tick the board is stored in a ring buffer. When the run
+//! ends the buffer is written to [`LOG_FILE`] as RON and printed to the log
+//! as a storyboard of top-down slices through the head's layer, so a run's
+//! end can be attached to a bug report.
+
+use std::{collections::VecDeque, fmt::Write, time::Duration};
+
+use bevy::prelude::*;
+
+use crate::{
+    persistence::PendingSaves, DeathEvent, Direction, Food, GameWonEvent, GridBounds, Position,
+    SnakeHead, SnakeSegment,
+};
+
+/// File the record is written to, in the working directory
+pub const LOG_FILE: &str = "last_death.ron";
+
+/// Seconds of play kept in the record
+const RECORD_SECONDS: f32 = 15.0;
+
+/// Hard cap on recorded ticks, 15 seconds at the fastest tick interval
+const MAX_FRAMES: usize = 150;
+
+/// Body segments stored per tick, longer snakes keep the ones nearest the head
+const MAX_SEGMENTS: usize = 64;
+
+/// The board as it was after a single tick
+#[derive(Debug, Clone)]
+struct BoardFrame {
+    tick: u64,
+    /// Fixed clock time of the tick, in seconds
+    time: f32,
+    direction: Direction,
+    head: IVec3,
+    segments: Vec<IVec3>,
+    food: Vec<IVec3>,
+}
+
+/// The most recent ticks of the current run
+#[derive(Resource, Debug, Default)]
+pub struct DeathLog {
+    frames: VecDeque<BoardFrame>,
+}
+
+impl DeathLog {
+    fn push(&mut self, frame: BoardFrame) {
+        let oldest = frame.time - RECORD_SECONDS;
+        while self
+            .frames
+            .front()
+            .is_some_and(|f| f.time < oldest || self.frames.len() >= MAX_FRAMES)
+        {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+    }
+
+    /// Serializes the record as RON
+    fn to_ron(&self) -> String {
+        let cell = |c: &IVec3| format!("({}, {}, {})", c.x, c.y, c.z);
+        let cells = |cs: &[IVec3]| cs.iter().map(cell).collect::<Vec<_>>().join(", ");
+
+        let mut ron = String::from("(\n    frames: [\n");
+        for frame in &self.frames {
+            let _ = writeln!(
+                ron,
+                "        (tick: {}, time: {:.3}, direction: {:?}, head: {}, segments: [{}], food: [{}]),",
+                frame.tick,
+                frame.time,
+                frame.direction,
+                cell(&frame.head),
+                cells(&frame.segments),
+                cells(&frame.food),
+            );
+        }
+        ron.push_str("    ],\n)\n");
+        ron
+    }
+
+    /// Top-down slices through the head's layer, `H` head, `o` body, `*` food
+    fn storyboard(&self, half_extent: IVec3) -> String {
+        let mut board = String::new();
+        for frame in &self.frames {
+            let y = frame.head.y;
+            let _ = writeln!(board, "tick {} y={y} {:?}", frame.tick, frame.direction);
+            for z in -half_extent.z..=half_extent.z {
+                for x in -half_extent.x..=half_extent.x {
+                    let cell = IVec3::new(x, y, z);
+                    board.push(if cell == frame.head {
+                        'H'
+                    } else if frame.segments.contains(&cell) {
+                        'o'
+                    } else if frame.food.contains(&cell) {
+                        '*'
+                    } else {
+                        '.'
+                    });
+                }
+                board.push('\n');
+            }
+        }
+        board
+    }
+}
+
+pub struct DeathLogPlugin;
+
+impl Plugin for DeathLogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DeathLog>()
+            .add_systems(crate::StartRun, clear_death_log)
+            .add_systems(FixedUpdate, record_board.in_set(crate::TickSet::Record))
+            .add_systems(Update, write_death_log);
+    }
+}
+
+fn clear_death_log(mut log: ResMut<DeathLog>) {
+    *log = DeathLog::default();
+}
+
+fn record_board(
+    time: Res<Time>,
+    tick: Res<crate::TickTimer>,
+    mut log: ResMut<DeathLog>,
+    head: Query<(&Position, &SnakeHead)>,
+    segments: Query<&Position, (With<SnakeSegment>, Without<SnakeHead>)>,
+    food: Query<&Position, With<Food>>,
+) {
+    let Ok((head_pos, SnakeHead(direction))) = head.get_single() else {
+        return;
+    };
+
+    let mut body: Vec<IVec3> = segments.iter().map(|p| p.0).collect();
+    if body.len() > MAX_SEGMENTS {
+        body.sort_by_key(|p| crate::grid_math::manhattan_distance(*p, head_pos.0));
+        body.truncate(MAX_SEGMENTS);
+    }
+
+    log.push(BoardFrame {
+        tick: tick.count,
+        time: time.elapsed_seconds(),
+        direction: *direction,
+        head: head_pos.0,
+        segments: body,
+        food: food.iter().map(|p| p.0).collect(),
+    });
+}
+
+/// Writes the record out once the run ends
+fn write_death_log(
+    mut won_reader: EventReader<GameWonEvent>,
+    mut death_reader: EventReader<DeathEvent>,
+    log: Res<DeathLog>,
+    bounds: Res<GridBounds>,
+    mut saves: ResMut<PendingSaves>,
+) {
+    if won_reader.is_empty() && death_reader.is_empty() {
+        return;
+    }
+    won_reader.clear();
+    death_reader.clear();
+
+    saves.save(LOG_FILE, log.to_ron().into_bytes(), Duration::ZERO);
+    info!(
+        target: "rsnake3d::write_death_log",
+        frames = log.frames.len(),
+        "Last ticks:\n{}",
+        log.storyboard(bounds.half_extent)
+    );
+}
@@ -0,0 +1,111 @@
+//! The six cardinal directions the snake can head in
+
+use bevy::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+    Forward,
+    Backward,
+}
+
+/// Minimum drag length, in logical pixels, before a drag counts as a turn
+const MIN_DRAG_DISTANCE: f32 = 50.0;
+
+impl Direction {
+    /// Converts a screen space mouse drag (y pointing up) into the cardinal
+    /// direction it points to, as seen from a camera with the given orientation
+    pub fn from_mouse_delta(delta: Vec2, camera_orientation: Quat) -> Option<Direction> {
+        if delta.length() < MIN_DRAG_DISTANCE {
+            return None;
+        }
+
+        Direction::from_screen(delta, camera_orientation)
+    }
+
+    /// The cardinal direction a screen space vector (y pointing up) points
+    /// to, as seen from a camera with the given orientation
+    pub fn from_screen(screen: Vec2, camera_orientation: Quat) -> Option<Direction> {
+        let camera_right = camera_orientation * Vec3::X;
+        let camera_up = camera_orientation * Vec3::Y;
+
+        Direction::try_from(camera_right * screen.x + camera_up * screen.y).ok()
+    }
+
+    /// The direction pointing the other way
+    pub fn opposite(self) -> Direction {
+        Direction::from(-IVec3::from(self))
+    }
+
+    /// Whether turning from `current` to `self` would reverse the snake into
+    /// its neck, the two vectors cancel out
+    pub fn reverses(self, current: Direction) -> bool {
+        IVec3::from(self) + IVec3::from(current) == IVec3::ZERO
+    }
+}
+
+impl From<IVec3> for Direction {
+    fn from(value: IVec3) -> Self {
+        match value {
+            IVec3::Y => Direction::Up,
+            IVec3::NEG_Y => Direction::Down,
+            IVec3::X => Direction::Right,
+            IVec3::NEG_X => Direction::Left,
+            IVec3::Z => Direction::Forward,
+            IVec3::NEG_Z => Direction::Backward,
+            _ => panic!("Invalid direction"),
+        }
+    }
+}
+
+impl TryFrom<Vec3> for Direction {
+    /// The rejected vector, either zero length or not finite
+    type Error = Vec3;
+
+    /// Snaps to the cardinal direction of the largest component
+    fn try_from(value: Vec3) -> Result<Self, Self::Error> {
+        if !value.is_finite() || value == Vec3::ZERO {
+            return Err(value);
+        }
+
+        let abs = value.abs();
+        let axis = if abs.x >= abs.y && abs.x >= abs.z {
+            IVec3::X * value.x.signum() as i32
+        } else if abs.y >= abs.z {
+            IVec3::Y * value.y.signum() as i32
+        } else {
+            IVec3::Z * value.z.signum() as i32
+        };
+
+        Ok(Direction::from(axis))
+    }
+}
+
+impl From<Direction> for Vec3 {
+    fn from(value: Direction) -> Self {
+        match value {
+            Direction::Up => Vec3::Y,
+            Direction::Down => Vec3::NEG_Y,
+            Direction::Right => Vec3::X,
+            Direction::Left => Vec3::NEG_X,
+            Direction::Forward => Vec3::Z,
+            Direction::Backward => Vec3::NEG_Z,
+        }
+    }
+}
+
+impl From<Direction> for IVec3 {
+    fn from(value: Direction) -> Self {
+        match value {
+            Direction::Up => IVec3::Y,
+            Direction::Down => IVec3::NEG_Y,
+            Direction::Right => IVec3::X,
+            Direction::Left => IVec3::NEG_X,
+            Direction::Forward => IVec3::Z,
+            Direction::Backward => IVec3::NEG_Z,
+        }
+    }
+}
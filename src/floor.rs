@@ -0,0 +1,113 @@
+//! Tiled arena floor
+//!
+//! Picking the cell under the cursor lives in [`crate::picking`].
+
+use bevy::prelude::*;
+
+use crate::{assist::DepthCues, GridBounds, GridConfig, Position, SnakeHead};
+
+/// Fraction of a cell covered by a floor tile, the rest shows as grid lines
+const TILE_FILL: f32 = 0.95;
+
+/// A floor tile, `position` is the arena cell right above it
+#[derive(Component, Debug, Default, Clone, Copy, Reflect)]
+pub struct GridCell {
+    pub position: IVec3,
+}
+
+/// Materials shared by every floor tile
+#[derive(Resource, Debug)]
+struct FloorMaterials {
+    base: Handle<StandardMaterial>,
+    /// Tiles sharing a row or column with the head
+    aligned: Handle<StandardMaterial>,
+}
+
+pub struct FloorPlugin;
+
+impl Plugin for FloorPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<GridCell>()
+            .add_systems(PostStartup, spawn_floor_tiles)
+            .add_systems(Update, shade_aligned_tiles);
+    }
+}
+
+fn spawn_floor_tiles(
+    mut commands: Commands,
+    grid: Res<GridConfig>,
+    bounds: Res<GridBounds>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let mesh = meshes.add(Mesh::from(shape::Plane::from_size(TILE_FILL)));
+    let material = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.08, 0.08, 0.16),
+        perceptual_roughness: 1.0,
+        ..default()
+    });
+    commands.insert_resource(FloorMaterials {
+        base: material.clone(),
+        aligned: materials.add(StandardMaterial {
+            base_color: Color::rgb(0.14, 0.14, 0.26),
+            perceptual_roughness: 1.0,
+            ..default()
+        }),
+    });
+
+    let half = bounds.half_extent;
+    for x in -half.x..=half.x {
+        for z in -half.z..=half.z {
+            let position = IVec3::new(x, -half.y, z);
+            let mut transform = grid.cell_transform(position);
+            transform.translation.y -= grid.cell_size / 2.0;
+
+            commands.spawn((
+                PbrBundle {
+                    mesh: mesh.clone(),
+                    material: material.clone(),
+                    transform,
+                    ..default()
+                },
+                GridCell { position },
+                Name::new("Floor Tile"),
+            ));
+        }
+    }
+
+    debug!(target: "rsnake3d::spawn_floor_tiles", ?half, "Spawned floor tiles");
+}
+
+/// Brightens the row and column of floor tiles lined up with the head, so
+/// its depth can be read off the floor from any camera angle
+///
+/// Tiles are matched on their cell coordinates rather than on the bounds,
+/// so the shading keeps following the head wherever it moves.
+fn shade_aligned_tiles(
+    depth_cues: Res<DepthCues>,
+    floor_materials: Option<Res<FloorMaterials>>,
+    moved_head: Query<&Position, (With<SnakeHead>, Changed<Position>)>,
+    all_heads: Query<&Position, With<SnakeHead>>,
+    mut tiles: Query<(&GridCell, &mut Handle<StandardMaterial>)>,
+) {
+    let Some(floor_materials) = floor_materials else {
+        return;
+    };
+    if moved_head.is_empty() && !depth_cues.is_changed() {
+        return;
+    }
+
+    let head = all_heads.get_single().ok().filter(|_| depth_cues.0);
+    for (cell, mut material) in tiles.iter_mut() {
+        let aligned =
+            head.is_some_and(|head| cell.position.x == head.0.x || cell.position.z == head.0.z);
+        let wanted = if aligned {
+            &floor_materials.aligned
+        } else {
+            &floor_materials.base
+        };
+        if *material != *wanted {
+            *material = wanted.clone();
+        }
+    }
+}
@@ -0,0 +1,105 @@
+//! Food left uneaten for too long moves to another cell
+//!
+//! In a 3D arena a food can sit out of sight for a long time, so every food
+//! counts the ticks it has been on the board and relocates once it reaches
+//! [`FoodLifetime`]. Relocating is not eating, nothing grows or scores. The
+//! food blinks during its last [`BLINK_TICKS`] ticks in place.
+
+use bevy::prelude::*;
+
+use crate::{
+    simulation::pick_food_cell, walls::Wall, Food, GameRng, GridBounds, Position, SnakeSegment,
+    TickTimer,
+};
+
+/// Ticks before a move during which the food blinks
+pub const BLINK_TICKS: u32 = 2;
+
+/// Blinks per second of a food about to move
+const BLINK_FREQUENCY: f32 = 4.0;
+
+/// Ticks a food stays in place before relocating, 0 keeps it forever
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub struct FoodLifetime(pub u32);
+
+impl Default for FoodLifetime {
+    fn default() -> Self {
+        FoodLifetime(30)
+    }
+}
+
+/// Ticks a food has spent in its current cell
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+pub struct FoodTimer {
+    pub age_ticks: u32,
+}
+
+pub struct FoodTimerPlugin;
+
+impl Plugin for FoodTimerPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<FoodLifetime>()
+            .register_type::<FoodTimer>()
+            .init_resource::<FoodLifetime>()
+            .add_systems(
+                FixedUpdate,
+                relocate_stale_food
+                    .before(crate::food_spawner)
+                    .in_set(crate::TickSet::PostTick),
+            )
+            .add_systems(Update, blink_stale_food);
+    }
+}
+
+/// Ages every food and moves the ones that reached their lifetime to a free
+/// cell, a food eaten this tick is already gone
+fn relocate_stale_food(
+    lifetime: Res<FoodLifetime>,
+    bounds: Res<GridBounds>,
+    mut rng: ResMut<GameRng>,
+    blocked: Query<&Position, (Or<(With<SnakeSegment>, With<Wall>)>, Without<Food>)>,
+    mut food: Query<(&mut Position, &mut FoodTimer), With<Food>>,
+    tick: Res<TickTimer>,
+) {
+    let _span = info_span!("relocate_stale_food", tick = tick.count).entered();
+
+    let mut occupied: Vec<IVec3> = blocked
+        .iter()
+        .chain(food.iter().map(|(pos, _)| pos))
+        .map(|p| p.0)
+        .collect();
+
+    for (mut pos, mut timer) in food.iter_mut() {
+        timer.age_ticks += 1;
+        if lifetime.0 == 0 || timer.age_ticks < lifetime.0 {
+            continue;
+        }
+
+        let Some(cell) = pick_food_cell(&mut rng.0, bounds.half_extent, &occupied, &[], 0) else {
+            continue;
+        };
+        debug!(target: "rsnake3d::relocate_stale_food", from = ?pos.0, to = ?cell);
+        occupied.push(cell);
+        pos.0 = cell;
+        timer.age_ticks = 0;
+    }
+}
+
+fn blink_stale_food(
+    time: Res<Time>,
+    lifetime: Res<FoodLifetime>,
+    mut food: Query<(&FoodTimer, &mut Visibility), With<Food>>,
+) {
+    let blink_off = (time.elapsed_seconds() * BLINK_FREQUENCY).fract() < 0.5;
+
+    for (timer, mut visibility) in food.iter_mut() {
+        let stale = lifetime.0 > 0 && timer.age_ticks + BLINK_TICKS >= lifetime.0;
+        let target = if stale && blink_off {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+        visibility.set_if_neq(target);
+    }
+}
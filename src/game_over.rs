@@ -0,0 +1,81 @@
+//! Game over overlay with the outcome, the final score, the food eaten and
+//! the restart prompt
+//!
+//! The panel is translucent so the frozen board stays visible behind it.
+//! Restarting itself is `restart_system`, this only draws.
+
+use bevy::prelude::*;
+
+use crate::{hud::UiAssets, AppState, FoodCounters, RunOutcome, Score};
+
+/// Tag for the root node of the overlay
+#[derive(Component, Debug, Default)]
+struct GameOverUi;
+
+pub struct GameOverPlugin;
+
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::GameOver), spawn_game_over_ui)
+            .add_systems(OnExit(AppState::GameOver), despawn_game_over_ui);
+    }
+}
+
+fn spawn_game_over_ui(
+    mut commands: Commands,
+    ui_assets: Res<UiAssets>,
+    score: Res<Score>,
+    counters: Res<FoodCounters>,
+    outcome: Res<RunOutcome>,
+) {
+    let text_style = |font_size| TextStyle {
+        font: ui_assets.font.clone(),
+        font_size,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(12.0),
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+                ..default()
+            },
+            GameOverUi,
+            Name::new("Game Over"),
+        ))
+        .with_children(|panel| {
+            let title = match *outcome {
+                RunOutcome::Lost => "Game Over",
+                RunOutcome::Won => "You Win",
+            };
+            panel.spawn(TextBundle::from_section(title, text_style(48.0)));
+            panel.spawn(TextBundle::from_section(
+                format!("Score {}", crate::hud::format_score(score.0)),
+                text_style(32.0),
+            ));
+            panel.spawn(TextBundle::from_section(
+                format!("Ate {}", *counters),
+                text_style(20.0),
+            ));
+            panel.spawn(TextBundle::from_section(
+                "Press R to restart",
+                text_style(24.0),
+            ));
+        });
+}
+
+fn despawn_game_over_ui(mut commands: Commands, ui: Query<Entity, With<GameOverUi>>) {
+    for ent in ui.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+}
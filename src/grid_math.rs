@@ -0,0 +1,308 @@
+//! Integer math helpers for cells of the arena grid
+
+use bevy::prelude::IVec3;
+
+/// Per axis distances between `a` and `b`, which cannot overflow even for
+/// cells at opposite `i32` limits
+fn axis_distances(a: IVec3, b: IVec3) -> [u32; 3] {
+    [a.x.abs_diff(b.x), a.y.abs_diff(b.y), a.z.abs_diff(b.z)]
+}
+
+/// Number of single axis steps needed to go from `a` to `b`, saturating at
+/// `u32::MAX`
+pub fn manhattan_distance(a: IVec3, b: IVec3) -> u32 {
+    axis_distances(a, b)
+        .into_iter()
+        .fold(0, u32::saturating_add)
+}
+
+/// Largest per axis distance between `a` and `b`
+pub fn chebyshev_distance(a: IVec3, b: IVec3) -> u32 {
+    axis_distances(a, b).into_iter().max().unwrap_or(0)
+}
+
+/// The six cells sharing a face with `pos`
+pub fn cardinal_neighbors(pos: IVec3) -> [IVec3; 6] {
+    [
+        pos + IVec3::X,
+        pos + IVec3::NEG_X,
+        pos + IVec3::Y,
+        pos + IVec3::NEG_Y,
+        pos + IVec3::Z,
+        pos + IVec3::NEG_Z,
+    ]
+}
+
+/// Whether `pos` lies inside the box spanning `-bounds..=bounds` on each axis
+pub fn is_in_bounds(pos: IVec3, bounds: IVec3) -> bool {
+    pos.cmpge(-bounds).all() && pos.cmple(bounds).all()
+}
+
+/// Wraps `pos` around the box spanning `-bounds..=bounds`, so leaving one
+/// face enters from the opposite one, negative coordinates included
+pub fn wrap_to_bounds(pos: IVec3, bounds: IVec3) -> IVec3 {
+    IVec3::new(
+        wrap_axis(pos.x, bounds.x),
+        wrap_axis(pos.y, bounds.y),
+        wrap_axis(pos.z, bounds.z),
+    )
+}
+
+/// Widened to `i64` so positions near the `i32` limits cannot overflow
+fn wrap_axis(value: i32, bound: i32) -> i32 {
+    let bound = i64::from(bound);
+    let size = bound * 2 + 1;
+    ((i64::from(value) + bound).rem_euclid(size) - bound) as i32
+}
+
+/// Closest cell to `pos` inside the box spanning `-bounds..=bounds`
+pub fn clamp_to_bounds(pos: IVec3, bounds: IVec3) -> IVec3 {
+    pos.clamp(-bounds, bounds)
+}
+
+/// Moves `pos` by `step`, sticking at the `i32` limits instead of overflowing
+pub fn saturating_step(pos: IVec3, step: IVec3) -> IVec3 {
+    IVec3::new(
+        pos.x.saturating_add(step.x),
+        pos.y.saturating_add(step.y),
+        pos.z.saturating_add(step.z),
+    )
+}
+
+/// Cells between `pos` and the closest face of the `-bounds..=bounds` box,
+/// negative once `pos` is outside of it
+pub fn distance_to_edge(pos: IVec3, bounds: IVec3) -> i32 {
+    [
+        bounds.x.saturating_sub(pos.x.saturating_abs()),
+        bounds.y.saturating_sub(pos.y.saturating_abs()),
+        bounds.z.saturating_sub(pos.z.saturating_abs()),
+    ]
+    .into_iter()
+    .min()
+    .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    const BOUNDS: IVec3 = IVec3::new(5, 3, 4);
+
+    #[test]
+    fn manhattan_distance_sums_the_axes() {
+        assert_eq!(manhattan_distance(IVec3::ZERO, IVec3::ZERO), 0);
+        assert_eq!(manhattan_distance(IVec3::new(1, 2, 3), IVec3::ZERO), 6);
+        assert_eq!(manhattan_distance(IVec3::new(-1, -2, -3), IVec3::ZERO), 6);
+        assert_eq!(
+            manhattan_distance(IVec3::new(-2, 0, 5), IVec3::new(3, -4, 5)),
+            9
+        );
+        assert_eq!(
+            manhattan_distance(IVec3::new(-2, 0, 5), IVec3::new(3, -4, 5)),
+            manhattan_distance(IVec3::new(3, -4, 5), IVec3::new(-2, 0, 5))
+        );
+    }
+
+    #[test]
+    fn chebyshev_distance_takes_the_largest_axis() {
+        assert_eq!(chebyshev_distance(IVec3::ZERO, IVec3::ZERO), 0);
+        assert_eq!(chebyshev_distance(IVec3::new(1, -7, 3), IVec3::ZERO), 7);
+        assert_eq!(
+            chebyshev_distance(IVec3::new(-2, 0, 5), IVec3::new(3, -4, 5)),
+            5
+        );
+        assert_eq!(chebyshev_distance(IVec3::ONE, IVec3::NEG_ONE), 2);
+    }
+
+    #[test]
+    fn cardinal_neighbors_are_one_step_away() {
+        let pos = IVec3::new(-1, 4, -3);
+        let neighbors = cardinal_neighbors(pos);
+        for (i, neighbor) in neighbors.iter().enumerate() {
+            assert_eq!(manhattan_distance(*neighbor, pos), 1);
+            assert!(!neighbors[i + 1..].contains(neighbor));
+        }
+    }
+
+    #[test]
+    fn is_in_bounds_includes_the_faces() {
+        assert!(is_in_bounds(IVec3::ZERO, BOUNDS));
+        assert!(is_in_bounds(BOUNDS, BOUNDS));
+        assert!(is_in_bounds(-BOUNDS, BOUNDS));
+        assert!(is_in_bounds(IVec3::new(-5, 3, -4), BOUNDS));
+        assert!(!is_in_bounds(IVec3::new(6, 0, 0), BOUNDS));
+        assert!(!is_in_bounds(IVec3::new(0, -4, 0), BOUNDS));
+        assert!(!is_in_bounds(IVec3::new(0, 0, 5), BOUNDS));
+        assert!(!is_in_bounds(IVec3::splat(i32::MIN), BOUNDS));
+        assert!(!is_in_bounds(IVec3::splat(i32::MAX), BOUNDS));
+    }
+
+    #[test]
+    fn wrap_to_bounds_enters_from_the_opposite_face() {
+        assert_eq!(wrap_to_bounds(IVec3::ZERO, BOUNDS), IVec3::ZERO);
+        assert_eq!(wrap_to_bounds(BOUNDS, BOUNDS), BOUNDS);
+        assert_eq!(wrap_to_bounds(-BOUNDS, BOUNDS), -BOUNDS);
+        assert_eq!(
+            wrap_to_bounds(IVec3::new(6, 0, 0), BOUNDS),
+            IVec3::new(-5, 0, 0)
+        );
+        assert_eq!(
+            wrap_to_bounds(IVec3::new(-6, 0, 0), BOUNDS),
+            IVec3::new(5, 0, 0)
+        );
+        assert_eq!(
+            wrap_to_bounds(IVec3::new(0, 4, -5), BOUNDS),
+            IVec3::new(0, -3, 4)
+        );
+        // a whole arena size away lands on the same cell
+        assert_eq!(
+            wrap_to_bounds(IVec3::new(-13, 7, 9), BOUNDS),
+            IVec3::new(-2, 0, 0)
+        );
+    }
+
+    #[test]
+    fn wrap_to_bounds_does_not_overflow_at_the_limits() {
+        let wrapped = wrap_to_bounds(IVec3::new(i32::MIN, i32::MAX, i32::MIN), BOUNDS);
+        assert!(is_in_bounds(wrapped, BOUNDS));
+    }
+
+    #[test]
+    fn clamp_to_bounds_moves_to_the_closest_face() {
+        assert_eq!(
+            clamp_to_bounds(IVec3::new(1, -2, 3), BOUNDS),
+            IVec3::new(1, -2, 3)
+        );
+        assert_eq!(
+            clamp_to_bounds(IVec3::new(9, -9, 0), BOUNDS),
+            IVec3::new(5, -3, 0)
+        );
+        assert_eq!(clamp_to_bounds(IVec3::splat(i32::MIN), BOUNDS), -BOUNDS);
+        assert_eq!(clamp_to_bounds(IVec3::splat(i32::MAX), BOUNDS), BOUNDS);
+    }
+
+    #[test]
+    fn saturating_step_sticks_at_the_limits() {
+        assert_eq!(
+            saturating_step(IVec3::new(-1, 0, 1), IVec3::NEG_X),
+            IVec3::new(-2, 0, 1)
+        );
+        assert_eq!(
+            saturating_step(IVec3::new(i32::MAX, i32::MIN, 0), IVec3::new(1, -1, 1)),
+            IVec3::new(i32::MAX, i32::MIN, 1)
+        );
+    }
+
+    #[test]
+    fn distance_to_edge_counts_cells_to_the_closest_face() {
+        assert_eq!(distance_to_edge(IVec3::ZERO, BOUNDS), 3);
+        assert_eq!(distance_to_edge(IVec3::new(-4, 0, 0), BOUNDS), 1);
+        assert_eq!(distance_to_edge(BOUNDS, BOUNDS), 0);
+        assert_eq!(distance_to_edge(-BOUNDS, BOUNDS), 0);
+        assert_eq!(distance_to_edge(IVec3::new(0, -5, 0), BOUNDS), -2);
+        assert!(distance_to_edge(IVec3::splat(i32::MIN), BOUNDS) < 0);
+    }
+
+    /// Any coordinate, biased towards the `i32` limits and the arena
+    fn coordinate() -> impl Strategy<Value = i32> {
+        prop_oneof![
+            Just(i32::MIN),
+            Just(i32::MAX),
+            i32::MIN..i32::MIN + 8,
+            i32::MAX - 8..=i32::MAX,
+            -64..64,
+            any::<i32>(),
+        ]
+    }
+
+    fn cell() -> impl Strategy<Value = IVec3> {
+        (coordinate(), coordinate(), coordinate()).prop_map(|(x, y, z)| IVec3::new(x, y, z))
+    }
+
+    fn small_cell() -> impl Strategy<Value = IVec3> {
+        (-64..64, -64..64, -64..64).prop_map(|(x, y, z)| IVec3::new(x, y, z))
+    }
+
+    fn arena() -> impl Strategy<Value = IVec3> {
+        (0..32, 0..32, 0..32).prop_map(|(x, y, z)| IVec3::new(x, y, z))
+    }
+
+    fn unit_step() -> impl Strategy<Value = IVec3> {
+        prop::sample::select(cardinal_neighbors(IVec3::ZERO).to_vec())
+    }
+
+    proptest! {
+        #[test]
+        fn distances_are_symmetric(a in cell(), b in cell()) {
+            prop_assert_eq!(manhattan_distance(a, b), manhattan_distance(b, a));
+            prop_assert_eq!(chebyshev_distance(a, b), chebyshev_distance(b, a));
+            prop_assert!(chebyshev_distance(a, b) <= manhattan_distance(a, b));
+        }
+
+        #[test]
+        fn manhattan_distance_obeys_the_triangle_inequality(
+            a in small_cell(),
+            b in small_cell(),
+            c in small_cell(),
+        ) {
+            prop_assert!(
+                manhattan_distance(a, c) <= manhattan_distance(a, b) + manhattan_distance(b, c)
+            );
+        }
+
+        #[test]
+        fn wrapped_cells_are_in_bounds(pos in cell(), bounds in arena()) {
+            prop_assert!(is_in_bounds(wrap_to_bounds(pos, bounds), bounds));
+        }
+
+        #[test]
+        fn wrapping_keeps_cells_inside_the_arena(
+            (pos, bounds) in arena().prop_flat_map(|b| {
+                let cell = (-b.x..=b.x, -b.y..=b.y, -b.z..=b.z)
+                    .prop_map(|(x, y, z)| IVec3::new(x, y, z));
+                (cell, Just(b))
+            }),
+        ) {
+            prop_assert_eq!(wrap_to_bounds(pos, bounds), pos);
+        }
+
+        #[test]
+        fn wrapping_repeats_every_arena_size(
+            pos in small_cell(),
+            bounds in arena(),
+            laps in -3..=3,
+        ) {
+            let shifted = pos + (bounds * 2 + IVec3::ONE) * laps;
+            prop_assert_eq!(wrap_to_bounds(shifted, bounds), wrap_to_bounds(pos, bounds));
+        }
+
+        #[test]
+        fn clamped_cells_are_in_bounds_and_closest(pos in cell(), bounds in arena()) {
+            let clamped = clamp_to_bounds(pos, bounds);
+            prop_assert!(is_in_bounds(clamped, bounds));
+            if is_in_bounds(pos, bounds) {
+                prop_assert_eq!(clamped, pos);
+            }
+        }
+
+        #[test]
+        fn saturating_steps_never_overflow(pos in cell(), step in unit_step()) {
+            let next = saturating_step(pos, step);
+            prop_assert!(manhattan_distance(pos, next) <= 1);
+            if let (Some(x), Some(y), Some(z)) = (
+                pos.x.checked_add(step.x),
+                pos.y.checked_add(step.y),
+                pos.z.checked_add(step.z),
+            ) {
+                prop_assert_eq!(next, IVec3::new(x, y, z));
+            }
+        }
+
+        #[test]
+        fn distance_to_edge_is_negative_only_outside(pos in cell(), bounds in arena()) {
+            prop_assert_eq!(distance_to_edge(pos, bounds) >= 0, is_in_bounds(pos, bounds));
+        }
+    }
+}
@@ -0,0 +1,427 @@
+//! Screen space overlays drawn on top of the arena
+
+use std::{collections::VecDeque, time::Duration};
+
+use bevy::prelude::*;
+
+use crate::{AppState, BuildInfo, Score, SlowMotion, SnakeSegment, StartRun, TickTimer};
+
+/// Number of frames the FPS average is taken over
+const FPS_WINDOW: usize = 60;
+
+/// Below this the FPS counter turns red
+const LOW_FPS: f32 = 30.0;
+
+/// Stores the assets shared by every UI node
+#[derive(Resource)]
+pub struct UiAssets {
+    pub font: Handle<Font>,
+}
+
+/// Whether the developer overlay (FPS, build info, debug cheats) is on, F11 toggles it
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub struct DebugOverlay(pub bool);
+
+impl Default for DebugOverlay {
+    fn default() -> Self {
+        DebugOverlay(cfg!(debug_assertions))
+    }
+}
+
+/// Rolling frame time statistics
+#[derive(Resource, Debug, Default)]
+pub struct FpsMeter {
+    pub fps: f32,
+    pub frame_times: VecDeque<f32>,
+}
+
+impl FpsMeter {
+    /// Average frame time over the window, in seconds
+    pub fn average_frame_time(&self) -> f32 {
+        if self.frame_times.is_empty() {
+            return 0.0;
+        }
+        self.frame_times.iter().sum::<f32>() / self.frame_times.len() as f32
+    }
+}
+
+/// Tag for the FPS counter text
+#[derive(Component, Debug, Default)]
+struct FpsText;
+
+/// Tag for the score text
+#[derive(Component, Debug, Default)]
+struct ScoreText;
+
+/// Time spent playing the current run, frozen once it ends
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RunTime(pub Duration);
+
+/// Tag for the snake length and run time text
+#[derive(Component, Debug, Default)]
+struct RunStatsText;
+
+/// Tag for the slow motion countdown text
+#[derive(Component, Debug, Default)]
+struct SlowMotionText;
+
+/// Seconds a toast stays on screen
+const TOAST_SECONDS: f32 = 2.0;
+
+/// Shows a short message in the middle of the screen
+#[derive(Event, Debug, Clone)]
+pub struct Toast(pub String);
+
+/// Tag for the toast text, `timer` counts down until it is cleared
+#[derive(Component, Debug, Default)]
+struct ToastText {
+    timer: Timer,
+}
+
+/// Tag for the build version text
+#[derive(Component, Debug, Default)]
+struct BuildText;
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<DebugOverlay>()
+            .init_resource::<DebugOverlay>()
+            .init_resource::<FpsMeter>()
+            .init_resource::<RunTime>()
+            .add_event::<Toast>()
+            .add_systems(Startup, load_ui_assets)
+            .add_systems(StartRun, reset_run_time)
+            .add_systems(
+                PostStartup,
+                (
+                    setup_fps_text,
+                    setup_build_text,
+                    setup_score_text,
+                    setup_run_stats_text,
+                    setup_slow_motion_text,
+                    setup_toast_text,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    (
+                        measure_fps,
+                        toggle_debug_overlay,
+                        show_debug_overlay,
+                        update_fps_text,
+                    )
+                        .chain(),
+                    update_score_text,
+                    tick_run_time.run_if(in_state(AppState::Playing)),
+                    update_run_stats_text.after(tick_run_time),
+                    update_slow_motion_text,
+                    show_toast,
+                ),
+            );
+    }
+}
+
+fn load_ui_assets(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(UiAssets {
+        font: asset_server.load("fonts/DejaVuSansMono.ttf"),
+    });
+}
+
+fn setup_fps_text(mut commands: Commands, ui_assets: Res<UiAssets>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: ui_assets.font.clone(),
+                font_size: 16.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            right: Val::Px(8.0),
+            ..default()
+        }),
+        FpsText,
+        Name::new("FPS Counter"),
+    ));
+}
+
+fn setup_build_text(mut commands: Commands, ui_assets: Res<UiAssets>, build_info: Res<BuildInfo>) {
+    commands.spawn((
+        TextBundle::from_section(
+            build_info.to_string(),
+            TextStyle {
+                font: ui_assets.font.clone(),
+                font_size: 12.0,
+                color: Color::GRAY,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(8.0),
+            right: Val::Px(8.0),
+            ..default()
+        }),
+        BuildText,
+        Name::new("Build Info"),
+    ));
+}
+
+fn setup_score_text(mut commands: Commands, ui_assets: Res<UiAssets>) {
+    commands.spawn((
+        TextBundle::from_section(
+            format_score(0),
+            TextStyle {
+                font: ui_assets.font.clone(),
+                font_size: 28.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            left: Val::Px(8.0),
+            ..default()
+        }),
+        ScoreText,
+        Name::new("Score"),
+    ));
+}
+
+fn update_score_text(score: Res<Score>, mut text: Query<&mut Text, With<ScoreText>>) {
+    if !score.is_changed() {
+        return;
+    }
+
+    for mut text in text.iter_mut() {
+        text.sections[0].value = format_score(score.0);
+    }
+}
+
+fn setup_run_stats_text(mut commands: Commands, ui_assets: Res<UiAssets>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: ui_assets.font.clone(),
+                font_size: 20.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(44.0),
+            left: Val::Px(8.0),
+            ..default()
+        }),
+        RunStatsText,
+        Name::new("Run Stats"),
+    ));
+}
+
+fn reset_run_time(mut run_time: ResMut<RunTime>) {
+    *run_time = RunTime::default();
+}
+
+fn tick_run_time(time: Res<Time>, mut run_time: ResMut<RunTime>) {
+    run_time.0 += time.delta();
+}
+
+/// Formats a duration as minutes and seconds, 83 seconds become `"1:23"`
+pub fn format_run_time(run_time: Duration) -> String {
+    let seconds = run_time.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Rewrites the text only when the length or the shown second changed
+fn update_run_stats_text(
+    run_time: Res<RunTime>,
+    segments: Query<(), With<SnakeSegment>>,
+    mut text: Query<&mut Text, With<RunStatsText>>,
+) {
+    let value = format!(
+        "Length {}  {}",
+        segments.iter().count(),
+        format_run_time(run_time.0)
+    );
+
+    for mut text in text.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+fn setup_slow_motion_text(mut commands: Commands, ui_assets: Res<UiAssets>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: ui_assets.font.clone(),
+                font_size: 20.0,
+                color: Color::rgb(0.4, 0.6, 1.0),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(68.0),
+            left: Val::Px(8.0),
+            ..default()
+        }),
+        SlowMotionText,
+        Name::new("Slow Motion"),
+    ));
+}
+
+fn update_slow_motion_text(
+    slow_motion: Res<SlowMotion>,
+    mut text: Query<&mut Text, With<SlowMotionText>>,
+) {
+    if !slow_motion.is_changed() {
+        return;
+    }
+
+    for mut text in text.iter_mut() {
+        text.sections[0].value = match slow_motion.remaining_ticks {
+            0 => String::new(),
+            ticks => format!("Slow motion: {ticks}"),
+        };
+    }
+}
+
+fn setup_toast_text(mut commands: Commands, ui_assets: Res<UiAssets>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: ui_assets.font.clone(),
+                font_size: 32.0,
+                color: Color::rgb(1.0, 0.9, 0.5),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Percent(20.0),
+            left: Val::Percent(50.0),
+            ..default()
+        }),
+        ToastText::default(),
+        Name::new("Toast"),
+    ));
+}
+
+/// Displays the latest toast and clears it once its time is up
+fn show_toast(
+    time: Res<Time>,
+    mut toasts: EventReader<Toast>,
+    mut text: Query<(&mut Text, &mut ToastText)>,
+) {
+    let Ok((mut text, mut toast)) = text.get_single_mut() else {
+        return;
+    };
+
+    if let Some(Toast(message)) = toasts.read().last() {
+        text.sections[0].value = message.clone();
+        toast.timer = Timer::from_seconds(TOAST_SECONDS, TimerMode::Once);
+    } else if toast.timer.tick(time.delta()).just_finished() {
+        text.sections[0].value.clear();
+    }
+}
+
+/// Formats a score with thousands separators, `1234567` becomes `"1,234,567"`
+pub fn format_score(score: u32) -> String {
+    let digits = score.to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+
+    formatted
+}
+
+fn measure_fps(time: Res<Time>, mut meter: ResMut<FpsMeter>) {
+    let delta = time.delta_seconds();
+    if delta <= 0.0 {
+        return;
+    }
+
+    if meter.frame_times.len() == FPS_WINDOW {
+        meter.frame_times.pop_front();
+    }
+    meter.frame_times.push_back(delta);
+
+    let average = meter.average_frame_time();
+    meter.fps = if average > 0.0 { 1.0 / average } else { 0.0 };
+}
+
+fn toggle_debug_overlay(keys: Res<Input<KeyCode>>, mut overlay: ResMut<DebugOverlay>) {
+    if keys.just_pressed(KeyCode::F11) {
+        overlay.0 = !overlay.0;
+    }
+}
+
+fn show_debug_overlay(
+    overlay: Res<DebugOverlay>,
+    mut text: Query<&mut Visibility, Or<(With<FpsText>, With<BuildText>)>>,
+) {
+    if !overlay.is_changed() {
+        return;
+    }
+
+    for mut visibility in text.iter_mut() {
+        *visibility = if overlay.0 {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+/// Shows the frame rate and the ticks discarded during hitches
+fn update_fps_text(
+    meter: Res<FpsMeter>,
+    tick: Res<TickTimer>,
+    mut text: Query<&mut Text, With<FpsText>>,
+) {
+    let Ok(mut text) = text.get_single_mut() else {
+        return;
+    };
+
+    let section = &mut text.sections[0];
+    section.value = format!(
+        "{:.0} fps {:.1} ms\n{} ticks discarded",
+        meter.fps,
+        meter.average_frame_time() * 1000.0,
+        tick.discarded
+    );
+    section.style.color = if meter.fps < LOW_FPS {
+        Color::RED
+    } else {
+        Color::WHITE
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_are_grouped_by_thousands() {
+        assert_eq!(format_score(0), "0");
+        assert_eq!(format_score(999), "999");
+        assert_eq!(format_score(1000), "1,000");
+        assert_eq!(format_score(999_999), "999,999");
+        assert_eq!(format_score(1_000_000), "1,000,000");
+        assert_eq!(format_score(u32::MAX), "4,294,967,295");
+    }
+}
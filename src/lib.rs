@@ -0,0 +1,3131 @@
+//! rsnake3d, a snake game on a 3D grid
+//!
+//! [`SnakeGamePlugin`] adds the whole game to a Bevy app, the `bevypoco`
+//! binary is little more than that plugin and `DefaultPlugins`, and
+//! `examples/embedded.rs` runs it inside a host app. Hosts can follow a run
+//! through the components and events of the [`prelude`], which only grows in
+//! minor releases.
+//!
+//! The rules themselves do not need Bevy's ECS: [`Simulation`] plays the
+//! game one tick at a time, for bots and offline analysis, see
+//! `examples/bot.rs`.
+
+pub mod direction;
+pub mod grid_math;
+pub mod simulation;
+pub mod turns;
+
+mod actions;
+mod assist;
+mod board_hash;
+#[cfg(debug_assertions)]
+mod console;
+mod death_log;
+mod floor;
+mod food_timer;
+mod game_over;
+mod hud;
+mod locks;
+mod menu;
+mod milestones;
+mod pause;
+mod persistence;
+mod picking;
+mod quit;
+#[cfg(feature = "scripting")]
+mod scripting;
+mod self_check;
+mod sounds;
+mod tooltip;
+mod validate;
+mod walls;
+mod zoom;
+
+use std::time::Duration;
+
+use bevy::{
+    core_pipeline::{bloom::BloomSettings, tonemapping::Tonemapping},
+    ecs::schedule::ScheduleLabel,
+    prelude::*,
+    render::view::ColorGrading,
+    time::TimeSystem,
+    window::PrimaryWindow,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use simulation::{
+    pick_food_cell, FoodKind, GOLDEN_CHANCE, GOLDEN_GROWTH, GOLDEN_POINTS, OUROBOROS_MIN_LENGTH,
+    SLOW_MOTION_CHANCE, START_CELLS,
+};
+use turns::{TurnBuffer, TurnPolicy};
+
+use locks::{KeyFor, KeysAndLocks, LockAssets, Locked, Unlocked};
+use walls::Wall;
+
+pub use direction::Direction;
+pub use simulation::{Rules, Simulation, TickOutcome};
+
+/// Everything needed to run the game inside another app
+pub mod prelude {
+    pub use crate::{
+        direction::Direction,
+        simulation::{board_hash, FoodKind, Rules, Simulation, TickOutcome, OUROBOROS_MIN_LENGTH},
+        turns::{TurnBuffer, TurnPolicy, MAX_QUEUED_TURNS},
+        AppState, DeathEvent, DeathReason, EatEvent, Food, GameWonEvent, Position, Score,
+        SnakeGamePlugin, SnakeHead, SnakeSegment, WinCause,
+    };
+}
+
+/// Identifies the binary, so saved files can be traced back to it
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+struct BuildInfo {
+    version: &'static str,
+    git_hash: &'static str,
+}
+
+impl Default for BuildInfo {
+    fn default() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            git_hash: env!("RSNAKE3D_GIT_HASH"),
+        }
+    }
+}
+
+impl std::fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "v{} ({})", self.version, self.git_hash)
+    }
+}
+
+/// Stores the assets for the game
+#[derive(Resource)]
+struct SnakeAssets {
+    snake_material: Handle<StandardMaterial>,
+    food_material: Handle<StandardMaterial>,
+    slow_motion_material: Handle<StandardMaterial>,
+    golden_material: Handle<StandardMaterial>,
+
+    head_mesh: Handle<Mesh>,
+    snout_mesh: Handle<Mesh>,
+    tail_mesh: Handle<Mesh>,
+    corner_mesh: Handle<Mesh>,
+    // tail_angle_mesh: Handle<Mesh>,
+    food_mesh: Handle<Mesh>,
+    danger_sphere_mesh: Handle<Mesh>,
+    spawn_ring_mesh: Handle<Mesh>,
+    /// Shared by every spawn ring, from opaque to fully faded, see
+    /// [`SPAWN_RING_FADE_STEPS`]
+    spawn_ring_materials: Vec<Handle<StandardMaterial>>,
+    wall_mesh: Handle<Mesh>,
+    wall_material: Handle<StandardMaterial>,
+}
+
+/// Glow of the regular food, slightly above 1.0 so the bloom pass picks it up
+const FOOD_EMISSIVE: Color = Color::rgb_linear(1.5, 0.05, 0.05);
+
+/// Glow of the slow motion food
+const SLOW_MOTION_EMISSIVE: Color = Color::rgb_linear(0.1, 0.3, 1.5);
+
+/// Glow of the golden food
+const GOLDEN_EMISSIVE: Color = Color::rgb_linear(1.8, 1.4, 0.2);
+
+/// Bundles of graphics options, `Custom` once any single option is edited
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+enum GraphicsPreset {
+    /// Classic cube meshes with no shadows, bloom, fog, MSAA, glow or particles
+    Low,
+    /// Shadows, MSAA, glow and particles, without bloom and fog
+    Medium,
+    /// Everything on
+    #[default]
+    High,
+    Custom,
+}
+
+impl GraphicsPreset {
+    /// The option values a preset stands for, `None` for `Custom`
+    fn settings(self) -> Option<GraphicsSettings> {
+        let settings = match self {
+            GraphicsPreset::Low => GraphicsSettings {
+                preset: self,
+                shadows: false,
+                bloom: false,
+                bloom_intensity: 0.15,
+                fog: false,
+                msaa: false,
+                classic_meshes: true,
+                glow: false,
+                particles: false,
+            },
+            GraphicsPreset::Medium => GraphicsSettings {
+                preset: self,
+                shadows: true,
+                bloom: false,
+                bloom_intensity: 0.15,
+                fog: false,
+                msaa: true,
+                classic_meshes: false,
+                glow: true,
+                particles: true,
+            },
+            GraphicsPreset::High => GraphicsSettings {
+                preset: self,
+                shadows: true,
+                bloom: true,
+                bloom_intensity: 0.15,
+                fog: true,
+                msaa: true,
+                classic_meshes: false,
+                glow: true,
+                particles: true,
+            },
+            GraphicsPreset::Custom => return None,
+        };
+        Some(settings)
+    }
+}
+
+/// User tweakable rendering options
+#[derive(Resource, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Resource)]
+struct GraphicsSettings {
+    preset: GraphicsPreset,
+    shadows: bool,
+    bloom: bool,
+    bloom_intensity: f32,
+    fog: bool,
+    msaa: bool,
+    /// Draws every body segment as a cube, bends included
+    classic_meshes: bool,
+    /// Lets food shine with its emissive color
+    glow: bool,
+    /// Spawn rings around new food
+    particles: bool,
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        GraphicsPreset::default().settings().unwrap()
+    }
+}
+
+/// Replaces pulsing and sliding animations with static cues when enabled
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+struct ReducedMotion(bool);
+
+/// Tag for the camera rendering the arena
+#[derive(Component, Debug, Default)]
+struct MainCamera;
+
+/// Tag for the light casting the arena shadows
+#[derive(Component, Debug, Default)]
+struct MainLight;
+
+/// Describes how grid cells are laid out in the world
+#[derive(Resource, Debug, Clone, Reflect)]
+#[reflect(Resource)]
+struct GridConfig {
+    /// World space side length of a single cell
+    cell_size: f32,
+    /// World space center of the cell at `IVec3::ZERO`
+    origin: Vec3,
+}
+
+impl Default for GridConfig {
+    fn default() -> Self {
+        Self {
+            cell_size: 1.0,
+            origin: Vec3::ZERO,
+        }
+    }
+}
+
+impl GridConfig {
+    /// World space center of a cell
+    fn cell_to_world(&self, cell: IVec3) -> Vec3 {
+        self.origin + cell.as_vec3() * self.cell_size
+    }
+
+    /// Cell containing a world space point
+    fn world_to_cell(&self, world: Vec3) -> IVec3 {
+        ((world - self.origin) / self.cell_size).round().as_ivec3()
+    }
+
+    /// Transform placing a unit sized mesh so it fills a cell
+    fn cell_transform(&self, cell: IVec3) -> Transform {
+        Transform::from_translation(self.cell_to_world(cell))
+            .with_scale(Vec3::splat(self.cell_size))
+    }
+}
+
+/// Where the arena sits in the world, `--floor-anchor` picks `Floor`
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+enum GridAnchor {
+    /// The cell at `IVec3::ZERO` is centered on the world origin
+    #[default]
+    Centered,
+    /// The bottom face of the arena lies on world y = 0
+    Floor,
+}
+
+impl GridAnchor {
+    fn from_args(mut args: impl Iterator<Item = String>) -> Self {
+        if args.any(|arg| arg == "--floor-anchor") {
+            GridAnchor::Floor
+        } else {
+            GridAnchor::Centered
+        }
+    }
+
+    /// World space center of the cell at `IVec3::ZERO` for an arena of the given size
+    fn origin(self, cell_size: f32, half_extent: IVec3) -> Vec3 {
+        match self {
+            GridAnchor::Centered => Vec3::ZERO,
+            GridAnchor::Floor => Vec3::Y * (half_extent.y as f32 + 0.5) * cell_size,
+        }
+    }
+}
+
+/// What happens to a head leaving the arena, `--wrap` picks `Wrap`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+enum WrapMode {
+    /// The faces are walls, leaving the arena ends the run
+    #[default]
+    Walls,
+    /// Leaving through a face enters through the opposite one
+    Wrap,
+}
+
+impl WrapMode {
+    fn from_args(mut args: impl Iterator<Item = String>) -> Self {
+        if args.any(|arg| arg == "--wrap") {
+            WrapMode::Wrap
+        } else {
+            WrapMode::Walls
+        }
+    }
+}
+
+/// Half size of the playable arena, cells range over `-half_extent..=half_extent`
+#[derive(Resource, Debug, Clone, Copy, Reflect)]
+#[reflect(Resource)]
+struct GridBounds {
+    half_extent: IVec3,
+    wrap: WrapMode,
+}
+
+impl Default for GridBounds {
+    /// Same arena as a headless [`Simulation`](crate::Simulation) plays in
+    fn default() -> Self {
+        Self {
+            half_extent: Rules::default().half_extent,
+            wrap: WrapMode::default(),
+        }
+    }
+}
+
+impl GridBounds {
+    /// Cells between `pos` and the closest arena face, 0 when on the edge
+    fn distance_to_edge(&self, pos: IVec3) -> i32 {
+        grid_math::distance_to_edge(pos, self.half_extent)
+    }
+
+    /// Cell one step from `pos`, across to the opposite face when wrapping,
+    /// sticking at the `i32` limits instead of overflowing
+    fn step(&self, pos: IVec3, direction: Direction) -> IVec3 {
+        let next = grid_math::saturating_step(pos, IVec3::from(direction));
+        match self.wrap {
+            WrapMode::Walls => next,
+            WrapMode::Wrap => grid_math::wrap_to_bounds(next, self.half_extent),
+        }
+    }
+
+    /// Offset from `from` to `to`, the short way across a face when wrapping
+    /// so neighbours on opposite faces are one cell apart
+    fn offset(&self, from: IVec3, to: IVec3) -> IVec3 {
+        match self.wrap {
+            WrapMode::Walls => to - from,
+            WrapMode::Wrap => grid_math::wrap_to_bounds(to - from, self.half_extent),
+        }
+    }
+}
+
+/// Source of every random choice of the game, the same seed and inputs
+/// replay the same food sequence
+#[derive(Resource, Debug)]
+struct GameRng(StdRng, u64);
+
+impl GameRng {
+    fn seeded(seed: u64) -> Self {
+        GameRng(StdRng::seed_from_u64(seed), seed)
+    }
+
+    /// The seed the game was started with
+    fn seed(&self) -> u64 {
+        self.1
+    }
+
+    /// Seeded from `--seed`, then `RSNAKE3D_SEED`, otherwise a random seed
+    fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let mut args = args.into_iter();
+        let mut flag = None;
+        while let Some(arg) = args.next() {
+            match arg.split_once('=') {
+                Some(("--seed", value)) => flag = Some(value.to_string()),
+                None if arg == "--seed" => flag = args.next(),
+                _ => {}
+            }
+        }
+
+        let seed = flag
+            .or_else(|| std::env::var("RSNAKE3D_SEED").ok())
+            .and_then(|seed| match seed.parse() {
+                Ok(seed) => Some(seed),
+                Err(err) => {
+                    warn!(target: "rsnake3d::game_rng", %seed, %err, "Invalid seed, using a random one");
+                    None
+                }
+            })
+            .unwrap_or_else(|| rand::thread_rng().gen());
+        info!(target: "rsnake3d::game_rng", seed, "Seeded the game");
+        GameRng::seeded(seed)
+    }
+}
+
+/// Tag for the translucent sphere warning about nearby walls
+#[derive(Component, Debug, Default)]
+struct DangerSphere;
+
+/// Stores the position in a grid like fashion
+#[derive(PartialEq, Component, Debug, Default, Clone, Copy, Reflect)]
+pub struct Position(pub IVec3);
+
+/// Tag for food
+#[derive(Component, Debug, Default)]
+pub struct Food;
+
+/// Tag for food that slows the game down when eaten
+#[derive(Component, Debug, Default)]
+struct SlowMotionFood;
+
+/// Tag for the rare food growing the snake by [`GOLDEN_GROWTH`] segments
+#[derive(Component, Debug, Default)]
+struct GoldenFood;
+
+/// Stores the direction the snake is moving in
+#[derive(Component, Debug, Reflect)]
+pub struct SnakeHead(pub Direction);
+
+impl Default for SnakeHead {
+    fn default() -> Self {
+        SnakeHead(Direction::Up)
+    }
+}
+
+/// Tag for snake segments
+#[derive(Component, Debug, Default)]
+pub struct SnakeSegment;
+
+/// Slide of a segment between two cells, drawn over the length of a tick
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
+struct SnakeInterpolation {
+    /// How far along the slide is, it has ended at 1
+    progress: f32,
+    from: Vec3,
+    to: Vec3,
+}
+
+impl Default for SnakeInterpolation {
+    /// An ended slide, the segment sits on its cell
+    fn default() -> Self {
+        Self {
+            progress: 1.0,
+            from: Vec3::ZERO,
+            to: Vec3::ZERO,
+        }
+    }
+}
+
+/// Stores the position of the last snake segment before it moved
+#[derive(Component, Debug, Default, Reflect)]
+struct LastSnakeSegment(Option<Position>);
+
+/// Components making up a snake segment
+///
+/// The render components are laid out flat rather than nesting a
+/// `PbrBundle`, so the bundle needs no `#[bundle]` field attribute.
+#[derive(Bundle)]
+struct SnakeSegmentBundle {
+    _segment: SnakeSegment,
+    _name: Name,
+    position: Position,
+    interpolation: SnakeInterpolation,
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+    transform: Transform,
+    global_transform: GlobalTransform,
+    visibility: Visibility,
+    inherited_visibility: InheritedVisibility,
+    view_visibility: ViewVisibility,
+}
+
+impl Default for SnakeSegmentBundle {
+    fn default() -> Self {
+        Self {
+            _name: Name::new("Snake Segment"),
+            _segment: SnakeSegment::default(),
+            position: Position::default(),
+            interpolation: SnakeInterpolation::default(),
+            mesh: Handle::default(),
+            material: Handle::default(),
+            transform: Transform::default(),
+            global_transform: GlobalTransform::default(),
+            visibility: Visibility::default(),
+            inherited_visibility: InheritedVisibility::default(),
+            view_visibility: ViewVisibility::default(),
+        }
+    }
+}
+
+impl SnakeSegmentBundle {
+    /// A tail segment at `pos`, with its mesh, material and transform filled in
+    fn at(pos: IVec3, grid: &GridConfig, assets: &SnakeAssets) -> Self {
+        Self {
+            position: Position(pos),
+            mesh: assets.tail_mesh.clone(),
+            material: assets.snake_material.clone(),
+            transform: grid.cell_transform(pos),
+            ..default()
+        }
+    }
+}
+
+/// The head is a segment that also knows where it is going
+///
+/// Composed as a tuple of the direction and a [`SnakeSegmentBundle`],
+/// tuples of bundles are bundles themselves.
+type SnakeHeadBundle = (SnakeHead, SnakeSegmentBundle);
+
+/// The tail tip, a segment remembering the cell it last vacated
+///
+/// Composed as a tuple like [`SnakeHeadBundle`].
+type SnakeLastSegmentBundle = (LastSnakeSegment, SnakeSegmentBundle);
+
+/// Components making up a food, flat like [`SnakeSegmentBundle`]
+#[derive(Bundle)]
+struct FoodBundle {
+    _name: Name,
+    _food: Food,
+    position: Position,
+    timer: food_timer::FoodTimer,
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+    transform: Transform,
+    global_transform: GlobalTransform,
+    visibility: Visibility,
+    inherited_visibility: InheritedVisibility,
+    view_visibility: ViewVisibility,
+}
+
+impl Default for FoodBundle {
+    fn default() -> Self {
+        Self {
+            _name: Name::new("Food"),
+            _food: Food::default(),
+            position: Position::default(),
+            timer: food_timer::FoodTimer::default(),
+            mesh: Handle::default(),
+            material: Handle::default(),
+            transform: Transform::default(),
+            global_transform: GlobalTransform::default(),
+            visibility: Visibility::default(),
+            inherited_visibility: InheritedVisibility::default(),
+            view_visibility: ViewVisibility::default(),
+        }
+    }
+}
+
+/// Why a run was won
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WinCause {
+    /// The head bit the tail tip
+    Ouroboros,
+}
+
+/// Notify that the run ended in a victory
+#[derive(Event, Debug, Clone, Copy)]
+pub struct GameWonEvent {
+    pub cause: WinCause,
+}
+
+/// Why the snake died
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeathReason {
+    /// The head moved into a body segment
+    SelfCollision,
+    /// The head left the arena
+    OutOfBounds,
+    /// The head ran into a wall block
+    HitWall,
+}
+
+/// Notify that the snake died, ending the run
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DeathEvent {
+    pub reason: DeathReason,
+}
+
+/// How the last run ended, shown on the game over screen
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum RunOutcome {
+    #[default]
+    Lost,
+    Won,
+}
+
+/// Top level mode of the app
+#[derive(States, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AppState {
+    /// Before the first run, the arena waits empty for the player to start
+    #[default]
+    MainMenu,
+    Playing,
+    /// The run is on hold, the clock driving the ticks is stopped
+    Paused,
+    /// The run ended, in a death or a victory, the board stays frozen
+    GameOver,
+}
+
+/// Runs once whenever a new run starts, from the main menu or after a game
+/// over, but not when resuming from a pause
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+struct StartRun;
+
+fn run_start_run_schedule(world: &mut World) {
+    world.run_schedule(StartRun);
+}
+
+/// Transparency levels a spawn ring fades through, each with its own
+/// material so rings never allocate materials while they fade
+const SPAWN_RING_FADE_STEPS: usize = 8;
+
+/// Expanding ring drawing the eye to a freshly spawned food
+#[derive(Component, Debug)]
+struct SpawnRingFlash {
+    timer: Timer,
+}
+
+impl Default for SpawnRingFlash {
+    fn default() -> Self {
+        Self {
+            timer: Timer::from_seconds(0.3, TimerMode::Once),
+        }
+    }
+}
+
+/// When enabled, body segments with nothing below them fall one cell per tick
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+struct GravitySegments(bool);
+
+/// Enables winning by biting exactly the tail tip once the snake is long enough
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+struct OuroborosRule(bool);
+
+/// Chebyshev radius around the starting snake kept clear of the first food
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+struct SpawnProtection(u32);
+
+impl Default for SpawnProtection {
+    fn default() -> Self {
+        SpawnProtection(2)
+    }
+}
+
+/// Ticks of slow motion left, the tick interval is stretched while non zero
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+struct SlowMotion {
+    remaining_ticks: u32,
+}
+
+/// How much longer a tick lasts during slow motion
+const SLOW_MOTION_FACTOR: f32 = 2.0;
+
+/// Ticks of slow motion granted by a single pickup
+const SLOW_MOTION_TICKS: u32 = 10;
+
+/// Snake segments from the head at index 0 to the tail tip at the end
+#[derive(Resource, Debug, Default, Clone, PartialEq, Eq)]
+struct SnakeOrder(Vec<Entity>);
+
+/// Segments still to grow, eats owing more than one segment grow one per tick
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+struct PendingGrowth(u32);
+
+/// How many foods the board is kept topped up with
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+struct FoodSettings {
+    count: usize,
+}
+
+impl Default for FoodSettings {
+    fn default() -> Self {
+        Self { count: 1 }
+    }
+}
+
+/// Points scored during the current run
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub struct Score(pub u32);
+
+/// Food of each kind eaten during the current run, unlike the score these
+/// are raw counts
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+struct FoodCounters {
+    regular: u32,
+    slow_motion: u32,
+    key: u32,
+    unlocked: u32,
+    golden: u32,
+}
+
+impl FoodCounters {
+    fn count(&self, kind: FoodKind) -> u32 {
+        match kind {
+            FoodKind::Regular => self.regular,
+            FoodKind::SlowMotion => self.slow_motion,
+            FoodKind::Key => self.key,
+            FoodKind::Unlocked => self.unlocked,
+            FoodKind::Golden => self.golden,
+        }
+    }
+
+    fn record(&mut self, kind: FoodKind) {
+        match kind {
+            FoodKind::Regular => self.regular += 1,
+            FoodKind::SlowMotion => self.slow_motion += 1,
+            FoodKind::Key => self.key += 1,
+            FoodKind::Unlocked => self.unlocked += 1,
+            FoodKind::Golden => self.golden += 1,
+        }
+    }
+}
+
+impl std::fmt::Display for FoodCounters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} regular, {} slow motion, {} keys, {} unlocked, {} golden",
+            self.count(FoodKind::Regular),
+            self.count(FoodKind::SlowMotion),
+            self.count(FoodKind::Key),
+            self.count(FoodKind::Unlocked),
+            self.count(FoodKind::Golden)
+        )
+    }
+}
+
+/// Notify that the food has been eaten
+#[derive(Event)]
+pub struct EatEvent {
+    pub kind: FoodKind,
+}
+
+fn load_meshes(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    // mut asset_server: ResMut<AssetServer>,
+) {
+    let head_mesh = meshes.add(Mesh::from(shape::Cube { size: 0.8 }));
+    // sticks out of the face of the head pointing where it moves
+    let snout_mesh = meshes.add(Mesh::from(shape::Box::new(0.4, 0.2, 0.4)));
+    let tail_mesh = meshes.add(Mesh::from(shape::Cube { size: 0.65 }));
+    // rounded, so a bend in the body reads as a joint rather than a box
+    let corner_mesh = meshes.add(Mesh::from(shape::UVSphere {
+        radius: 0.4,
+        sectors: 16,
+        stacks: 12,
+    }));
+
+    let food_mesh = meshes.add(
+        Mesh::try_from(shape::Icosphere {
+            radius: 0.4,
+            subdivisions: 2,
+        })
+        .expect("food icosphere subdivisions are within the supported range"),
+    );
+
+    let danger_sphere_mesh = meshes.add(Mesh::from(shape::UVSphere {
+        radius: 1.5,
+        sectors: 24,
+        stacks: 16,
+    }));
+
+    let spawn_ring_mesh = meshes.add(Mesh::from(shape::Torus {
+        radius: 0.5,
+        ring_radius: 0.04,
+        subdivisions_segments: 32,
+        subdivisions_sides: 8,
+    }));
+
+    let spawn_ring_materials = (0..SPAWN_RING_FADE_STEPS)
+        .map(|step| {
+            materials.add(StandardMaterial {
+                base_color: Color::rgba(
+                    1.,
+                    1.,
+                    1.,
+                    1. - step as f32 / SPAWN_RING_FADE_STEPS as f32,
+                ),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            })
+        })
+        .collect();
+
+    let wall_mesh = meshes.add(Mesh::from(shape::Cube { size: 1.0 }));
+
+    commands.insert_resource(SnakeAssets {
+        snake_material: materials.add(StandardMaterial {
+            base_color: Color::rgb(0., 0.7, 0.),
+            // unlit: true,
+            ..default()
+        }),
+        food_material: materials.add(StandardMaterial {
+            base_color: Color::rgb(1., 0., 0.),
+            emissive: FOOD_EMISSIVE,
+            // unlit: true,
+            ..default()
+        }),
+        slow_motion_material: materials.add(StandardMaterial {
+            base_color: Color::rgb(0.2, 0.4, 1.),
+            emissive: SLOW_MOTION_EMISSIVE,
+            ..default()
+        }),
+        golden_material: materials.add(StandardMaterial {
+            base_color: Color::rgb(1., 0.85, 0.2),
+            emissive: GOLDEN_EMISSIVE,
+            metallic: 0.8,
+            ..default()
+        }),
+
+        head_mesh,
+        snout_mesh,
+        tail_mesh,
+        corner_mesh,
+        // tail_angle_mesh: head_mesh,
+        food_mesh,
+        danger_sphere_mesh,
+        spawn_ring_mesh,
+        spawn_ring_materials,
+        wall_mesh,
+        wall_material: materials.add(StandardMaterial {
+            base_color: Color::rgb(0.45, 0.45, 0.5),
+            perceptual_roughness: 0.9,
+            ..default()
+        }),
+    });
+}
+
+fn setup_window(
+    mut primary_window_q: Query<&mut Window, With<PrimaryWindow>>,
+    build_info: Res<BuildInfo>,
+) {
+    let Ok(mut window) = primary_window_q.get_single_mut() else {
+        return;
+    };
+    window.title = if cfg!(debug_assertions) {
+        format!("Snake DDD {}", *build_info)
+    } else {
+        "Snake DDD".to_string()
+    };
+    // window.resolution = (500.0, 500.0).into();
+}
+
+/// Moves the grid origin where the anchor puts it, before anything is spawned
+fn apply_grid_anchor(
+    anchor: Res<GridAnchor>,
+    bounds: Res<GridBounds>,
+    mut grid: ResMut<GridConfig>,
+) {
+    grid.origin = anchor.origin(grid.cell_size, bounds.half_extent);
+    debug!(target: "rsnake3d::apply_grid_anchor", ?anchor, origin = ?grid.origin);
+}
+
+fn setup_camera(mut commands: Commands, grid: Res<GridConfig>) {
+    commands.spawn((
+        Camera3dBundle {
+            camera: Camera {
+                hdr: true,
+                ..default()
+            },
+            tonemapping: Tonemapping::TonyMcMapface,
+            transform: Transform::from_translation(
+                grid.origin + Vec3::ONE.normalize() * zoom::DEFAULT_DISTANCE,
+            )
+            .looking_at(grid.origin, Vec3::Y),
+            ..default()
+        },
+        MainCamera,
+    ));
+}
+
+fn setup_light(mut commands: Commands, grid: Res<GridConfig>) {
+    commands.spawn((
+        DirectionalLightBundle {
+            directional_light: DirectionalLight {
+                illuminance: 8000.,
+                shadows_enabled: true,
+                ..default()
+            },
+            transform: Transform::from_translation(grid.origin + Vec3::new(4.0, 10.0, 6.0))
+                .looking_at(grid.origin, Vec3::Y),
+            ..default()
+        },
+        MainLight,
+    ));
+}
+
+/// Writes a newly selected preset through to the individual options,
+/// or flips the preset to `Custom` when an option is edited by hand
+fn sync_graphics_preset(
+    mut settings: ResMut<GraphicsSettings>,
+    mut last_preset: Local<Option<GraphicsPreset>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    let preset = settings.preset;
+    if *last_preset != Some(preset) {
+        *last_preset = Some(preset);
+        if let Some(preset_settings) = preset.settings() {
+            if *settings != preset_settings {
+                *settings = preset_settings;
+            }
+        }
+        return;
+    }
+
+    if preset.settings().is_some_and(|s| s != *settings) {
+        settings.preset = GraphicsPreset::Custom;
+        *last_preset = Some(GraphicsPreset::Custom);
+    }
+}
+
+/// Reconfigures the camera, light and food materials whenever the graphics
+/// settings change
+fn apply_graphics_settings(
+    mut commands: Commands,
+    settings: Res<GraphicsSettings>,
+    mut msaa: ResMut<Msaa>,
+    clear_color: Res<ClearColor>,
+    mut camera: Query<(Entity, Option<&mut BloomSettings>), With<MainCamera>>,
+    mut light: Query<&mut DirectionalLight, With<MainLight>>,
+    (snake_assets, mut materials): (Res<SnakeAssets>, ResMut<Assets<StandardMaterial>>),
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    let Ok((camera_ent, bloom)) = camera.get_single_mut() else {
+        return;
+    };
+
+    match (settings.bloom, bloom) {
+        (true, Some(mut bloom)) => bloom.intensity = settings.bloom_intensity,
+        (true, None) => {
+            commands.entity(camera_ent).insert(BloomSettings {
+                intensity: settings.bloom_intensity,
+                ..default()
+            });
+        }
+        (false, Some(_)) => {
+            commands.entity(camera_ent).remove::<BloomSettings>();
+        }
+        (false, None) => {}
+    }
+
+    if settings.fog {
+        commands.entity(camera_ent).insert(FogSettings {
+            color: clear_color.0,
+            falloff: FogFalloff::Linear {
+                start: 15.0,
+                end: 40.0,
+            },
+            ..default()
+        });
+    } else {
+        commands.entity(camera_ent).remove::<FogSettings>();
+    }
+
+    *msaa = if settings.msaa {
+        Msaa::Sample4
+    } else {
+        Msaa::Off
+    };
+
+    for mut light in light.iter_mut() {
+        light.shadows_enabled = settings.shadows;
+    }
+
+    let glowing = [
+        (&snake_assets.food_material, FOOD_EMISSIVE),
+        (&snake_assets.slow_motion_material, SLOW_MOTION_EMISSIVE),
+        (&snake_assets.golden_material, GOLDEN_EMISSIVE),
+    ];
+    for (handle, glow) in glowing {
+        let emissive = if settings.glow { glow } else { Color::BLACK };
+        match materials.get(handle) {
+            Some(material) if material.emissive != emissive => {}
+            _ => continue,
+        }
+        if let Some(material) = materials.get_mut(handle) {
+            material.emissive = emissive;
+        }
+    }
+
+    debug!(target: "rsnake3d::apply_graphics_settings", ?settings);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn setup_scene(
+    mut commands: Commands,
+    grid: Res<GridConfig>,
+    bounds: Res<GridBounds>,
+    protection: Res<SpawnProtection>,
+    mut rng: ResMut<GameRng>,
+    mut order: ResMut<SnakeOrder>,
+    snake_assets: Res<SnakeAssets>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    walls: Query<&Position, With<Wall>>,
+) {
+    // HEAD
+    let danger_material = materials.add(StandardMaterial {
+        base_color: Color::rgba(0., 1., 0., 0.1),
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        ..default()
+    });
+
+    let mut head_segment = SnakeSegmentBundle::at(START_CELLS[0], &grid, &snake_assets);
+    head_segment._name = Name::new("Snake Head");
+    head_segment.mesh = snake_assets.head_mesh.clone();
+
+    let head = commands
+        .spawn::<SnakeHeadBundle>((SnakeHead(Direction::Up), head_segment))
+        .with_children(|head| {
+            head.spawn((
+                PbrBundle {
+                    mesh: snake_assets.snout_mesh.clone(),
+                    material: snake_assets.snake_material.clone(),
+                    transform: Transform::from_xyz(0., 0.45, 0.),
+                    ..default()
+                },
+                Name::new("Snout"),
+            ));
+            head.spawn((
+                PbrBundle {
+                    mesh: snake_assets.danger_sphere_mesh.clone(),
+                    material: danger_material,
+                    ..default()
+                },
+                DangerSphere,
+                Name::new("Danger Sphere"),
+            ));
+        })
+        .id();
+
+    debug!(target: "rsnake3d::setup_scene", "Spawned head");
+
+    // Starting tail
+    let tail = commands
+        .spawn::<SnakeLastSegmentBundle>((
+            LastSnakeSegment::default(),
+            SnakeSegmentBundle::at(START_CELLS[1], &grid, &snake_assets),
+        ))
+        .id();
+    order.0 = vec![head, tail];
+
+    debug!(target: "rsnake3d::setup_scene", "Spawned tail");
+
+    let occupied: Vec<IVec3> = START_CELLS
+        .into_iter()
+        .chain(walls.iter().map(|p| p.0))
+        .collect();
+    let Some(food) = pick_food_cell(
+        &mut rng.0,
+        bounds.half_extent,
+        &occupied,
+        &START_CELLS,
+        protection.0,
+    ) else {
+        warn!(target: "rsnake3d::setup_scene", "No room for the first food");
+        return;
+    };
+
+    commands.spawn(FoodBundle {
+        position: Position(food),
+        mesh: snake_assets.food_mesh.clone(),
+        material: snake_assets.food_material.clone(),
+        transform: grid.cell_transform(food),
+        ..default()
+    });
+
+    debug!(target: "rsnake3d::setup_scene", "Spawned food at {:?}", food);
+}
+
+/// Places entities on their cell, segments slide there over the tick unless
+/// reduced motion is on
+fn position_translation(
+    time: Res<Time>,
+    fixed_time: Res<Time<Fixed>>,
+    grid: Res<GridConfig>,
+    reduced_motion: Res<ReducedMotion>,
+    mut query: Query<(&Position, &mut Transform, Option<&mut SnakeInterpolation>)>,
+) {
+    let step = time.delta_seconds() / fixed_time.timestep().as_secs_f32();
+
+    for (Position(pos), mut transform, interpolation) in query.iter_mut() {
+        transform.translation = match interpolation {
+            Some(mut slide) if !reduced_motion.0 && slide.progress < 1.0 => {
+                slide.progress = (slide.progress + step).min(1.0);
+                slide.from.lerp(slide.to, slide.progress)
+            }
+            _ => grid.cell_to_world(*pos),
+        };
+        transform.scale = Vec3::splat(grid.cell_size);
+    }
+}
+
+/// Starts a slide towards the new cell of every segment that moved this tick,
+/// segments jumping further than a cell (wrapping, teleports) snap instead
+fn start_segment_slides(
+    grid: Res<GridConfig>,
+    mut segments: Query<(&Position, &Transform, &mut SnakeInterpolation), Changed<Position>>,
+) {
+    for (Position(pos), transform, mut slide) in segments.iter_mut() {
+        let from = transform.translation;
+        let to = grid.cell_to_world(*pos);
+        let jumped = grid_math::manhattan_distance(grid.world_to_cell(from), *pos) > 1;
+        *slide = SnakeInterpolation {
+            progress: if jumped { 1.0 } else { 0.0 },
+            from,
+            to,
+        };
+    }
+}
+
+/// Rotation pointing the local Y axis of a body segment at `ahead`, the
+/// segment before it in [`SnakeOrder`], `None` unless the two are neighbours
+fn segment_facing(bounds: &GridBounds, pos: IVec3, ahead: IVec3) -> Option<Quat> {
+    let offset = bounds.offset(pos, ahead);
+    (grid_math::manhattan_distance(offset, IVec3::ZERO) == 1)
+        .then(|| Quat::from_rotation_arc(Vec3::Y, offset.as_vec3()))
+}
+
+/// Turns body segments along the body, their local Y axis pointing at the
+/// segment ahead of them, the tail tip included
+///
+/// Segments stacked on one cell or pulled apart by gravity keep their
+/// rotation.
+fn orient_segments(
+    order: Res<SnakeOrder>,
+    bounds: Res<GridBounds>,
+    positions: Query<&Position, With<SnakeSegment>>,
+    mut segments: Query<&mut Transform, (With<SnakeSegment>, Without<SnakeHead>)>,
+) {
+    for window in order.0.windows(2) {
+        let [ahead, ent] = window else {
+            continue;
+        };
+        let (Ok(ahead), Ok(pos)) = (positions.get(*ahead), positions.get(*ent)) else {
+            continue;
+        };
+        let Some(rotation) = segment_facing(&bounds, pos.0, ahead.0) else {
+            continue;
+        };
+        let Ok(mut transform) = segments.get_mut(*ent) else {
+            continue;
+        };
+        if transform.rotation != rotation {
+            transform.rotation = rotation;
+        }
+    }
+}
+
+/// Draws body segments where the snake bends with the corner mesh, straight
+/// runs and every segment with classic meshes keep the tail mesh
+fn update_segment_meshes(
+    order: Res<SnakeOrder>,
+    bounds: Res<GridBounds>,
+    graphics: Res<GraphicsSettings>,
+    snake_assets: Res<SnakeAssets>,
+    mut segments: Query<(&Position, &mut Handle<Mesh>), (With<SnakeSegment>, Without<SnakeHead>)>,
+    positions: Query<&Position, With<SnakeSegment>>,
+) {
+    for window in order.0.windows(3) {
+        let [ahead, ent, behind] = window else {
+            continue;
+        };
+        let (Ok(ahead), Ok(behind)) = (positions.get(*ahead), positions.get(*behind)) else {
+            continue;
+        };
+        let Ok((pos, mut mesh)) = segments.get_mut(*ent) else {
+            continue;
+        };
+
+        let bends = bounds.offset(behind.0, pos.0) != bounds.offset(pos.0, ahead.0);
+        let target = if bends && !graphics.classic_meshes {
+            &snake_assets.corner_mesh
+        } else {
+            &snake_assets.tail_mesh
+        };
+        if *mesh != *target {
+            *mesh = target.clone();
+        }
+    }
+}
+
+/// Rotation pointing the local Y axis of the head, the side its snout is
+/// on, along `direction`, like `segment_facing` does for the body
+fn head_facing(direction: Direction) -> Quat {
+    Quat::from_rotation_arc(Vec3::Y, Vec3::from(direction))
+}
+
+/// Turns the head to face the direction it moves in, every frame so
+/// `tilt_head_towards_turn` can lean it from there
+fn rotate_head(mut head: Query<(&SnakeHead, &mut Transform)>) {
+    for (SnakeHead(direction), mut transform) in head.iter_mut() {
+        transform.rotation = head_facing(*direction);
+    }
+}
+
+/// Checks that freshly spawned entities start where their `Position` says
+fn check_spawn_transforms(
+    grid: Res<GridConfig>,
+    query: Query<(&Position, &Transform), Added<Position>>,
+) {
+    for (Position(pos), transform) in query.iter() {
+        debug_assert!(
+            transform.translation.distance(grid.cell_to_world(*pos)) < 1e-4,
+            "entity at {pos:?} spawned at {:?}",
+            transform.translation
+        );
+    }
+}
+
+/// Fades the danger sphere from green to red as the head nears a wall
+fn update_danger_sphere(
+    bounds: Res<GridBounds>,
+    head: Query<&Position, With<SnakeHead>>,
+    spheres: Query<&Handle<StandardMaterial>, With<DangerSphere>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    /// Distance from a wall, in cells, at which the sphere starts turning red
+    const WARNING_DISTANCE: f32 = 2.0;
+
+    let Ok(Position(head_pos)) = head.get_single() else {
+        return;
+    };
+
+    let danger = match bounds.wrap {
+        WrapMode::Walls => {
+            let distance = bounds.distance_to_edge(*head_pos).max(0) as f32;
+            1.0 - (distance / WARNING_DISTANCE).clamp(0.0, 1.0)
+        }
+        WrapMode::Wrap => 0.0,
+    };
+
+    let color = Color::rgba(danger, 1.0 - danger, 0., 0.1);
+    for handle in spheres.iter() {
+        // get_mut flags the material for a GPU upload, skip it when idle
+        match materials.get(handle) {
+            Some(material) if material.base_color != color => {}
+            _ => continue,
+        }
+        if let Some(material) = materials.get_mut(handle) {
+            material.base_color = color;
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn eat_food(
+    mut commands: Commands,
+    mut eat_writer: EventWriter<EatEvent>,
+    food_position: Query<
+        (
+            Entity,
+            &Position,
+            Option<&SlowMotionFood>,
+            Option<&GoldenFood>,
+            Option<&KeyFor>,
+            Option<&Unlocked>,
+        ),
+        (With<Food>, Without<Locked>),
+    >,
+    locked: Query<(), With<Locked>>,
+    head_position: Query<&Position, With<SnakeHead>>,
+    mut slow_motion: ResMut<SlowMotion>,
+    tick: Res<TickTimer>,
+) {
+    let _span = info_span!("eat_food", tick = tick.count).entered();
+
+    let Ok(head_pos) = head_position.get_single() else {
+        return;
+    };
+
+    // locked food is inert, the head passes over it
+    let Some((ent, food_pos, slow_motion_food, golden_food, key, unlocked)) =
+        food_position.iter().find(|(_, pos, ..)| *pos == head_pos)
+    else {
+        return;
+    };
+
+    debug!(target: "rsnake3d::eat_food", head = ?head_pos, food = ?food_pos);
+    commands.entity(ent).despawn();
+    let kind = match key {
+        Some(KeyFor(target)) if locked.contains(*target) => {
+            locks::unlock(&mut commands, *target);
+            FoodKind::Key
+        }
+        _ if unlocked.is_some() => FoodKind::Unlocked,
+        _ if slow_motion_food.is_some() => {
+            // stacking pickups extend the effect rather than slowing further
+            slow_motion.remaining_ticks += SLOW_MOTION_TICKS;
+            debug!(target: "rsnake3d::eat_food", ?slow_motion);
+            FoodKind::SlowMotion
+        }
+        _ if golden_food.is_some() => FoodKind::Golden,
+        _ => FoodKind::Regular,
+    };
+    eat_writer.send(EatEvent { kind });
+    debug!(target: "rsnake3d::events", "Sent EatEvent");
+}
+
+fn tick_slow_motion(mut slow_motion: ResMut<SlowMotion>) {
+    slow_motion.remaining_ticks = slow_motion.remaining_ticks.saturating_sub(1);
+}
+
+/// Washes out the colors while slow motion is active
+fn desaturate_slow_motion(
+    slow_motion: Res<SlowMotion>,
+    mut camera: Query<&mut ColorGrading, With<MainCamera>>,
+) {
+    if !slow_motion.is_changed() {
+        return;
+    }
+
+    let saturation = if slow_motion.remaining_ticks > 0 {
+        0.6
+    } else {
+        1.0
+    };
+
+    for mut grading in camera.iter_mut() {
+        grading.post_saturation = saturation;
+    }
+}
+
+fn update_score(
+    mut eat_reader: EventReader<EatEvent>,
+    mut score: ResMut<Score>,
+    mut counters: ResMut<FoodCounters>,
+) {
+    for EatEvent { kind } in eat_reader.read() {
+        score.0 += kind.points();
+        counters.record(*kind);
+        debug!(target: "rsnake3d::update_score", score = score.0, ?kind);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn snake_growth(
+    mut commands: Commands,
+    mut eat_reader: EventReader<EatEvent>,
+    mut pending: ResMut<PendingGrowth>,
+    mut order: ResMut<SnakeOrder>,
+    last_segment: Query<(Entity, &LastSnakeSegment)>,
+    grid: Res<GridConfig>,
+    snake_assets: Res<SnakeAssets>,
+    tick: Res<TickTimer>,
+) {
+    let _span = info_span!("snake_growth", tick = tick.count).entered();
+
+    let Ok((last_segment_ent, LastSnakeSegment(Some(last_segment_pos)))) = last_segment.get_single() else {
+        return;
+    };
+
+    pending.0 += eat_reader
+        .read()
+        .map(|EatEvent { kind }| kind.growth())
+        .sum::<u32>();
+    // only one cell was vacated, so the snake grows by at most one per tick
+    // and the rest carries over to the next ticks
+    if pending.0 == 0 {
+        return;
+    }
+    pending.0 -= 1;
+
+    debug!(target: "rsnake3d::snake_growth", pending = pending.0, "Growing");
+    debug!(target: "rsnake3d::snake_growth", ?last_segment_ent, ?last_segment_pos);
+    // add new segment after last and move last component
+    // to the new one
+    commands
+        .entity(last_segment_ent)
+        .remove::<LastSnakeSegment>();
+
+    debug!(target: "rsnake3d::snake_growth", "Removed LastSnakeSegment from {:?}", last_segment_ent);
+
+    let tail = commands
+        .spawn::<SnakeLastSegmentBundle>((
+            LastSnakeSegment(None),
+            SnakeSegmentBundle::at(last_segment_pos.0, &grid, &snake_assets),
+        ))
+        .id();
+    order.0.push(tail);
+
+    debug!(target: "rsnake3d::snake_growth", "Spawned new tail segment at {:?}", last_segment_pos);
+}
+
+/// Tops the board up to the food count of [`FoodSettings`] once the tick
+/// has settled, a key does not count, its locked food does
+#[allow(clippy::too_many_arguments)]
+fn food_spawner(
+    mut commands: Commands,
+    settings: Res<FoodSettings>,
+    blocked: Query<&Position, Or<(With<SnakeSegment>, With<Wall>)>>,
+    food: Query<(&Position, Option<&KeyFor>), With<Food>>,
+    grid: Res<GridConfig>,
+    bounds: Res<GridBounds>,
+    snake_assets: Res<SnakeAssets>,
+    (keys_and_locks, lock_assets): (Res<KeysAndLocks>, Res<LockAssets>),
+    graphics: Res<GraphicsSettings>,
+    mut rng: ResMut<GameRng>,
+    tick: Res<TickTimer>,
+) {
+    let _span = info_span!("food_spawner", tick = tick.count).entered();
+
+    let counted = food.iter().filter(|(_, key)| key.is_none()).count();
+    let missing = settings.count.saturating_sub(counted);
+    if missing == 0 {
+        return;
+    }
+
+    // spawn protection only applies to the first food
+    let mut occupied: Vec<IVec3> = blocked
+        .iter()
+        .chain(food.iter().map(|(pos, _)| pos))
+        .map(|p| p.0)
+        .collect();
+    let rng = &mut rng.0;
+    for _ in 0..missing {
+        let Some(cell) = pick_food_cell(&mut rng, bounds.half_extent, &occupied, &[], 0) else {
+            warn!(target: "rsnake3d::food_spawner", "No room left for food");
+            return;
+        };
+        occupied.push(cell);
+
+        let lock = if keys_and_locks.0 && rng.gen_bool(locks::LOCK_CHANCE) {
+            pick_food_cell(&mut rng, bounds.half_extent, &occupied, &[], 0)
+        } else {
+            None
+        };
+
+        let spawned = if let Some(lock) = lock {
+            occupied.push(lock);
+            locks::spawn_pair(
+                &mut commands,
+                cell,
+                lock,
+                &grid,
+                &snake_assets,
+                &lock_assets,
+            );
+            vec![cell, lock]
+        } else {
+            let kind = if rng.gen_bool(SLOW_MOTION_CHANCE) {
+                FoodKind::SlowMotion
+            } else if rng.gen_bool(GOLDEN_CHANCE) {
+                FoodKind::Golden
+            } else {
+                FoodKind::Regular
+            };
+            let material = match kind {
+                FoodKind::SlowMotion => &snake_assets.slow_motion_material,
+                FoodKind::Golden => &snake_assets.golden_material,
+                _ => &snake_assets.food_material,
+            };
+
+            let mut food = commands.spawn(FoodBundle {
+                position: Position(cell),
+                mesh: snake_assets.food_mesh.clone(),
+                material: material.clone(),
+                transform: grid.cell_transform(cell),
+                ..default()
+            });
+            match kind {
+                FoodKind::SlowMotion => {
+                    food.insert(SlowMotionFood);
+                }
+                FoodKind::Golden => {
+                    food.insert(GoldenFood);
+                }
+                _ => {}
+            }
+            debug!(target: "rsnake3d::food_spawner", ?kind, "Spawned new food at {:?}", cell);
+            vec![cell]
+        };
+
+        if !graphics.particles {
+            continue;
+        }
+        for cell in spawned {
+            commands.spawn((
+                PbrBundle {
+                    mesh: snake_assets.spawn_ring_mesh.clone(),
+                    material: snake_assets.spawn_ring_materials[0].clone(),
+                    transform: Transform::from_translation(grid.cell_to_world(cell))
+                        .with_scale(Vec3::ZERO),
+                    ..default()
+                },
+                SpawnRingFlash::default(),
+                Name::new("Spawn Ring Flash"),
+            ));
+        }
+    }
+}
+
+/// Grows and fades the spawn rings, despawning them once done
+#[allow(clippy::type_complexity)]
+fn tick_spawn_ring_flash(
+    mut commands: Commands,
+    time: Res<Time>,
+    grid: Res<GridConfig>,
+    snake_assets: Res<SnakeAssets>,
+    mut flashes: Query<(
+        Entity,
+        &mut SpawnRingFlash,
+        &mut Transform,
+        &mut Handle<StandardMaterial>,
+    )>,
+) {
+    for (ent, mut flash, mut transform, mut material) in flashes.iter_mut() {
+        flash.timer.tick(time.delta());
+
+        if flash.timer.finished() {
+            commands.entity(ent).despawn();
+            continue;
+        }
+
+        let progress = flash.timer.percent();
+        transform.scale = Vec3::splat(2.0 * progress * grid.cell_size);
+        let step = spawn_ring_fade_step(progress);
+        material.set_if_neq(snake_assets.spawn_ring_materials[step].clone());
+    }
+}
+
+/// Index into [`SnakeAssets::spawn_ring_materials`] for a ring `progress`
+/// of the way through its flash
+fn spawn_ring_fade_step(progress: f32) -> usize {
+    ((progress * SPAWN_RING_FADE_STEPS as f32) as usize).min(SPAWN_RING_FADE_STEPS - 1)
+}
+
+/// Offers the turns of this frame to the turn buffer
+fn apply_turn_actions(
+    actions: Res<actions::ActionState>,
+    rules: Res<Rules>,
+    mut turns: ResMut<TurnBuffer>,
+    head: Query<&SnakeHead>,
+) {
+    let Ok(SnakeHead(heading)) = head.get_single() else {
+        return;
+    };
+
+    for direction in actions.turns() {
+        if turns.request(rules.turn_policy, *heading, direction) {
+            debug!(target: "rsnake3d::apply_turn_actions", ?direction);
+        }
+    }
+}
+
+/// Turns the head with the buffered turn this tick resolves to
+fn take_queued_turn(mut turns: ResMut<TurnBuffer>, mut head: Query<&mut SnakeHead>) {
+    let Ok(mut head) = head.get_single_mut() else {
+        return;
+    };
+    let Some(turn) = turns.take(head.0) else {
+        return;
+    };
+
+    head.0 = turn;
+    trace!(target: "rsnake3d::take_queued_turn", ?turn);
+}
+
+/// Moves the head one cell and every other segment into the cell of the
+/// segment ahead of it, walking [`SnakeOrder`] from the head to the tail tip
+fn snake_movement(
+    order: Res<SnakeOrder>,
+    head: Query<&SnakeHead>,
+    mut query_last: Query<&mut LastSnakeSegment>,
+    mut positions: Query<&mut Position, With<SnakeSegment>>,
+    bounds: Res<GridBounds>,
+    tick: Res<TickTimer>,
+) {
+    let _span = info_span!("snake_movement", tick = tick.count).entered();
+
+    let Some((&head_ent, body)) = order.0.split_first() else {
+        return;
+    };
+    let Ok(SnakeHead(snake_direction)) = head.get(head_ent) else {
+        return;
+    };
+    let Ok(mut head_position) = positions.get_mut(head_ent) else {
+        return;
+    };
+
+    // save position of head before moving it
+    let mut old_position = *head_position;
+    trace!(target: "rsnake3d::snake_movement", "Saving head_position at {:?}", &old_position);
+
+    // move head in direction
+    // with walls the head may leave the arena, check_boundary_collision ends the run then
+    head_position.0 = bounds.step(head_position.0, *snake_direction);
+
+    debug!(target: "rsnake3d::snake_movement", "Moved Head to {:?}", head_position.0);
+
+    // move all segments in snake to the next one, from the neck to the tail tip
+    for ent in body {
+        let Ok(mut pos) = positions.get_mut(*ent) else {
+            continue;
+        };
+        trace!(target: "rsnake3d::snake_movement", "Moved from {:?} to {:?}", *pos, old_position);
+        let tmp = *pos;
+        *pos = old_position;
+        old_position = tmp;
+    }
+
+    // what is left is the cell the tail tip vacated
+    if let Ok(mut last_segment) = query_last.get_single_mut() {
+        last_segment.0 = Some(old_position);
+        trace!(target: "rsnake3d::snake_movement", "Saving last segment at {:?}", old_position);
+    }
+}
+
+/// Drops floating body segments one cell towards the arena floor
+fn gravity_system(
+    gravity: Res<GravitySegments>,
+    bounds: Res<GridBounds>,
+    mut segments: Query<&mut Position, (With<SnakeSegment>, Without<SnakeHead>)>,
+    head: Query<&Position, With<SnakeHead>>,
+) {
+    if !gravity.0 {
+        return;
+    }
+
+    let mut occupied: Vec<IVec3> = segments
+        .iter()
+        .map(|p| p.0)
+        .chain(head.iter().map(|p| p.0))
+        .collect();
+
+    // settle from the bottom up so a whole column falls together
+    let mut falling: Vec<_> = segments.iter_mut().collect();
+    falling.sort_by_key(|p| p.0.y);
+
+    for mut pos in falling {
+        let below = pos.0 + IVec3::NEG_Y;
+        if below.y < -bounds.half_extent.y || occupied.contains(&below) {
+            continue;
+        }
+
+        trace!(target: "rsnake3d::gravity_system", "Segment falls from {:?}", pos.0);
+        if let Some(cell) = occupied.iter_mut().find(|c| **c == pos.0) {
+            *cell = below;
+        }
+        pos.0 = below;
+    }
+}
+
+/// Detects the head moving onto the cell the tail tip just left
+fn check_ouroboros(
+    rule: Res<OuroborosRule>,
+    head: Query<&Position, With<SnakeHead>>,
+    last_segment: Query<&LastSnakeSegment>,
+    segments: Query<(), With<SnakeSegment>>,
+    mut won_writer: EventWriter<GameWonEvent>,
+) {
+    if !rule.0 || segments.iter().count() < OUROBOROS_MIN_LENGTH {
+        return;
+    }
+
+    let (Ok(head_pos), Ok(LastSnakeSegment(Some(tail_pos)))) =
+        (head.get_single(), last_segment.get_single())
+    else {
+        return;
+    };
+
+    if head_pos == tail_pos {
+        won_writer.send(GameWonEvent {
+            cause: WinCause::Ouroboros,
+        });
+        debug!(target: "rsnake3d::events", "Sent GameWonEvent");
+    }
+}
+
+/// Ends the run once it is won, freezing the board like a death does
+fn handle_victory(
+    mut won_reader: EventReader<GameWonEvent>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut outcome: ResMut<RunOutcome>,
+    tick: Res<TickTimer>,
+    score: Res<Score>,
+    counters: Res<FoodCounters>,
+) {
+    let Some(GameWonEvent { cause }) = won_reader.read().next() else {
+        return;
+    };
+    won_reader.clear();
+
+    info!(
+        target: "rsnake3d::handle_victory",
+        ?cause,
+        ticks = tick.count,
+        score = score.0,
+        "Run won, ate {}",
+        *counters
+    );
+    *outcome = RunOutcome::Won;
+    next_state.set(AppState::GameOver);
+}
+
+/// Kills the snake when the head moved into its own body
+///
+/// Runs once every segment has moved, so following the tail into the cell
+/// it just left is not a collision.
+fn check_self_collision(
+    head: Query<&Position, With<SnakeHead>>,
+    segments: Query<&Position, (With<SnakeSegment>, Without<SnakeHead>)>,
+    mut death_writer: EventWriter<DeathEvent>,
+    tick: Res<TickTimer>,
+) {
+    let _span = info_span!("check_self_collision", tick = tick.count).entered();
+
+    let Ok(head_pos) = head.get_single() else {
+        return;
+    };
+
+    if segments.iter().any(|pos| pos == head_pos) {
+        debug!(target: "rsnake3d::check_self_collision", head = ?head_pos);
+        death_writer.send(DeathEvent {
+            reason: DeathReason::SelfCollision,
+        });
+        debug!(target: "rsnake3d::events", "Sent DeathEvent");
+    }
+}
+
+/// Kills the snake when the head left the arena
+fn check_boundary_collision(
+    bounds: Res<GridBounds>,
+    head: Query<&Position, With<SnakeHead>>,
+    mut death_writer: EventWriter<DeathEvent>,
+    tick: Res<TickTimer>,
+) {
+    let _span = info_span!("check_boundary_collision", tick = tick.count).entered();
+
+    let Ok(head_pos) = head.get_single() else {
+        return;
+    };
+
+    if !grid_math::is_in_bounds(head_pos.0, bounds.half_extent) {
+        debug!(target: "rsnake3d::check_boundary_collision", head = ?head_pos);
+        death_writer.send(DeathEvent {
+            reason: DeathReason::OutOfBounds,
+        });
+        debug!(target: "rsnake3d::events", "Sent DeathEvent");
+    }
+}
+
+/// Ends the run once the snake dies, whatever killed it
+///
+/// Collision checks only send [`DeathEvent`], this is the one place moving
+/// to [`AppState::GameOver`]. Sounds and effects react to the event in
+/// their own plugins.
+fn handle_death_event(
+    mut death_reader: EventReader<DeathEvent>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut outcome: ResMut<RunOutcome>,
+    tick: Res<TickTimer>,
+    score: Res<Score>,
+    counters: Res<FoodCounters>,
+) {
+    let Some(DeathEvent { reason }) = death_reader.read().next() else {
+        return;
+    };
+    death_reader.clear();
+
+    info!(
+        target: "rsnake3d::handle_death_event",
+        ?reason,
+        ticks = tick.count,
+        score = score.0,
+        "Run lost, ate {}",
+        *counters
+    );
+    *outcome = RunOutcome::Lost;
+    next_state.set(AppState::GameOver);
+}
+
+/// Clears the board once the player asks for a restart on the game over
+/// screen and starts a new run, `setup_scene` lays out the fresh board
+fn restart_system(
+    mut commands: Commands,
+    actions: Res<actions::ActionState>,
+    board: Query<Entity, Or<(With<SnakeSegment>, With<Food>)>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if !actions.pressed(actions::PlayerAction::Restart) {
+        return;
+    }
+
+    for ent in board.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+    info!(target: "rsnake3d::restart_system", "Restarting");
+    next_state.set(AppState::Playing);
+}
+
+/// Puts back the per run resources before a run starts
+fn reset_run(
+    mut score: ResMut<Score>,
+    mut counters: ResMut<FoodCounters>,
+    mut pending: ResMut<PendingGrowth>,
+    mut slow_motion: ResMut<SlowMotion>,
+    mut turns: ResMut<TurnBuffer>,
+    mut tick: ResMut<TickTimer>,
+) {
+    *score = Score::default();
+    *counters = FoodCounters::default();
+    *pending = PendingGrowth::default();
+    *slow_motion = SlowMotion::default();
+    turns.clear();
+    tick.count = 0;
+    tick.override_interval = None;
+    tick.interval = tick.start_interval;
+}
+
+/// Stages of the gameplay tick inside `FixedUpdate`
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum TickSet {
+    /// Moving, eating, growing and respawning food
+    Simulate,
+    /// Runs once the board is consistent again, spawns and despawns applied
+    PostTick,
+    /// Only reads the board, once the food spawned during `PostTick` is
+    /// applied too, so hashes, recordings and checks see the final state
+    Record,
+}
+
+/// The gameplay tick: its state, the `FixedUpdate` stages and the reset at
+/// the start of every run
+///
+/// Kept apart from the rendering side so tests can run ticks headless.
+struct TickPlugin;
+
+impl Plugin for TickPlugin {
+    fn build(&self, app: &mut App) {
+        // read by the tick too, for the segment meshes and spawn rings
+        app.init_resource::<GraphicsSettings>()
+            .init_resource::<Score>()
+            .init_resource::<FoodSettings>()
+            .init_resource::<PendingGrowth>()
+            .init_resource::<SnakeOrder>()
+            .init_resource::<FoodCounters>()
+            .init_resource::<GravitySegments>()
+            .init_resource::<OuroborosRule>()
+            .init_resource::<SlowMotion>()
+            .init_resource::<SpawnProtection>()
+            .init_resource::<Rules>()
+            .init_resource::<TurnBuffer>()
+            .init_resource::<TickTimer>()
+            .insert_resource(Time::<Fixed>::from_duration(TickTimer::default().interval))
+            .add_event::<EatEvent>()
+            .add_event::<GameWonEvent>()
+            .add_event::<DeathEvent>()
+            .add_systems(StartRun, (reset_run, setup_scene).chain())
+            .add_systems(
+                First,
+                (apply_tick_interval, discard_ticks)
+                    .chain()
+                    .after(TimeSystem),
+            )
+            .configure_sets(
+                FixedUpdate,
+                (
+                    TickSet::Simulate.run_if(in_state(AppState::Playing)),
+                    TickSet::PostTick.run_if(in_state(AppState::Playing)),
+                    TickSet::Record.run_if(in_state(AppState::Playing)),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    count_tick.before(tick_slow_motion),
+                    (snake_movement, eat_food, snake_growth).chain(),
+                    // the board hash of the tick reads the score
+                    update_score.after(eat_food),
+                    gravity_system.after(snake_movement),
+                    check_ouroboros.after(snake_movement),
+                    check_self_collision.after(snake_movement),
+                    check_boundary_collision.after(snake_movement),
+                    tick_slow_motion.before(snake_movement),
+                    take_queued_turn.before(snake_movement),
+                )
+                    .in_set(TickSet::Simulate),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    apply_deferred
+                        .after(TickSet::Simulate)
+                        .before(TickSet::PostTick),
+                    apply_deferred
+                        .after(TickSet::PostTick)
+                        .before(TickSet::Record),
+                ),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    (orient_segments, start_segment_slides).chain(),
+                    update_segment_meshes,
+                    food_spawner,
+                )
+                    .in_set(TickSet::PostTick),
+            );
+    }
+}
+
+/// Paces the gameplay tick, which runs in the `FixedUpdate` schedule
+///
+/// `apply_tick_interval` keeps the fixed timestep in sync with `interval`
+/// and caps the virtual clock maximum delta at `max_ticks_per_frame`
+/// intervals, one by default, so the snake never teleports several cells
+/// at once. Time that piles up above that during a hitch (asset loads,
+/// window drags) is discarded and tallied in `discarded`.
+#[derive(Resource, Debug, Reflect)]
+#[reflect(Resource)]
+struct TickTimer {
+    /// Base time between ticks, before temporary effects like slow motion
+    interval: Duration,
+    /// Interval at the start of a run, before any point is scored
+    start_interval: Duration,
+    /// How much shorter the interval gets per point scored
+    speed_up_per_point: Duration,
+    /// Shortest interval scoring can bring the tick down to
+    min_interval: Duration,
+    /// Interval set by hand from the console or a script, scoring leaves it
+    /// alone until the run ends
+    override_interval: Option<Duration>,
+    /// Most gameplay ticks a single rendered frame may run
+    max_ticks_per_frame: u32,
+    /// Gameplay ticks run since the run started
+    count: u64,
+    /// Ticks thrown away since startup, for diagnostics
+    discarded: u64,
+    /// Discarded time not yet adding up to a whole tick
+    discarded_time: Duration,
+}
+
+impl Default for TickTimer {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_millis(1300),
+            start_interval: Duration::from_millis(1300),
+            speed_up_per_point: Duration::from_millis(50),
+            min_interval: Duration::from_millis(200),
+            override_interval: None,
+            max_ticks_per_frame: 1,
+            count: 0,
+            discarded: 0,
+            discarded_time: Duration::ZERO,
+        }
+    }
+}
+
+impl TickTimer {
+    /// Pins the interval for the rest of the run, over difficulty scaling
+    fn set_override(&mut self, interval: Duration) {
+        self.override_interval = Some(interval);
+        self.interval = interval;
+    }
+
+    /// Tallies virtual time dropped by the maximum delta, `interval` being
+    /// the timestep it was dropped at
+    fn discard(&mut self, lost: Duration, interval: Duration) {
+        if interval.is_zero() {
+            return;
+        }
+        self.discarded_time += lost;
+        let ticks = self.discarded_time.as_nanos() / interval.as_nanos();
+        if ticks > 0 {
+            self.discarded += ticks as u64;
+            self.discarded_time -= interval * ticks as u32;
+            debug!(target: "rsnake3d::discard_ticks", ticks, total = self.discarded);
+        }
+    }
+}
+
+fn count_tick(mut tick: ResMut<TickTimer>) {
+    tick.count += 1;
+}
+
+/// Speeds the tick up as the score grows, following
+/// `max(min_interval, start_interval - score * speed_up_per_point)`, unless
+/// the interval was overridden by hand
+fn difficulty_scaling(score: Res<Score>, mut tick: ResMut<TickTimer>, mut last_score: Local<u32>) {
+    if score.0 == *last_score {
+        return;
+    }
+    *last_score = score.0;
+
+    let speed_up = tick.speed_up_per_point.saturating_mul(score.0);
+    let scaled = tick
+        .start_interval
+        .saturating_sub(speed_up)
+        .max(tick.min_interval);
+    tick.interval = tick.override_interval.unwrap_or(scaled);
+    debug!(target: "rsnake3d::difficulty_scaling", score = score.0, interval = ?tick.interval);
+}
+
+/// Keeps the fixed timestep in sync with the base interval and active effects
+fn apply_tick_interval(
+    tick: Res<TickTimer>,
+    slow_motion: Res<SlowMotion>,
+    mut fixed_time: ResMut<Time<Fixed>>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+) {
+    let interval = if slow_motion.remaining_ticks > 0 {
+        tick.interval.mul_f32(SLOW_MOTION_FACTOR)
+    } else {
+        tick.interval
+    };
+
+    // overstep stays below one timestep, so this allows exactly that many ticks
+    let max_delta = interval * tick.max_ticks_per_frame.max(1);
+    if fixed_time.timestep() != interval || virtual_time.max_delta() != max_delta {
+        fixed_time.set_timestep(interval);
+        virtual_time.set_max_delta(max_delta);
+        debug!(target: "rsnake3d::apply_tick_interval", ?interval, ?max_delta);
+    }
+}
+
+/// Counts the ticks the virtual clock maximum delta dropped this frame
+fn discard_ticks(
+    real_time: Res<Time<Real>>,
+    virtual_time: Res<Time<Virtual>>,
+    fixed_time: Res<Time<Fixed>>,
+    mut tick: ResMut<TickTimer>,
+) {
+    let unclamped = real_time
+        .delta()
+        .mul_f64(virtual_time.effective_speed_f64());
+    let lost = unclamped.saturating_sub(virtual_time.delta());
+    if !lost.is_zero() {
+        tick.discard(lost, fixed_time.timestep());
+    }
+}
+
+/// The whole game, from the main menu to the game over screen
+///
+/// Expects the host to add `DefaultPlugins` with
+/// `WindowPlugin::close_when_requested` off, close requests go through the
+/// game's quit confirmation. Without [`SnakeGamePlugin::with_seed`] the run
+/// is seeded from `RSNAKE3D_SEED` or at random.
+#[derive(Debug, Clone, Default)]
+pub struct SnakeGamePlugin {
+    bounds: GridBounds,
+    anchor: GridAnchor,
+    seed: Option<u64>,
+    walls: walls::WallLayout,
+    validate: bool,
+}
+
+impl SnakeGamePlugin {
+    /// Set up from the command line of the `bevypoco` binary: `--seed`,
+    /// `--wrap`, `--walls`, `--floor-anchor` and `--validate`
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let args: Vec<String> = args.into_iter().collect();
+        Self {
+            bounds: GridBounds {
+                wrap: WrapMode::from_args(args.iter().cloned()),
+                ..default()
+            },
+            anchor: GridAnchor::from_args(args.iter().cloned()),
+            seed: Some(GameRng::from_args(args.iter().cloned()).seed()),
+            walls: walls::WallLayout::from_args(args.iter().cloned()),
+            validate: validate::enabled(args.iter().cloned()),
+        }
+    }
+
+    /// Plays in an arena spanning `-half_extent..=half_extent` on each axis
+    pub fn with_grid(mut self, half_extent: IVec3) -> Self {
+        self.bounds.half_extent = half_extent;
+        self
+    }
+
+    /// Seeds every random choice, the same seed and inputs replay the same run
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+}
+
+impl Plugin for SnakeGamePlugin {
+    fn build(&self, app: &mut App) {
+        let rng = match self.seed {
+            Some(seed) => GameRng::seeded(seed),
+            None => GameRng::from_args(std::iter::empty()),
+        };
+
+        app.register_type::<Position>()
+            .register_type::<Direction>()
+            .register_type::<SnakeHead>()
+            .register_type::<LastSnakeSegment>()
+            .register_type::<SnakeInterpolation>()
+            .register_type::<GraphicsPreset>()
+            .register_type::<GraphicsSettings>()
+            .register_type::<ReducedMotion>()
+            .register_type::<TickTimer>()
+            .register_type::<GridConfig>()
+            .register_type::<GridAnchor>()
+            .register_type::<GridBounds>()
+            .register_type::<WrapMode>()
+            .register_type::<GravitySegments>()
+            .register_type::<OuroborosRule>()
+            .register_type::<SlowMotion>()
+            .register_type::<SpawnProtection>()
+            .register_type::<Rules>()
+            .register_type::<TurnPolicy>()
+            .register_type::<Score>()
+            .register_type::<FoodSettings>()
+            .register_type::<PendingGrowth>()
+            .register_type::<FoodKind>()
+            .register_type::<FoodCounters>()
+            .init_resource::<BuildInfo>()
+            .init_resource::<ReducedMotion>()
+            .init_resource::<GridConfig>()
+            .insert_resource(self.anchor)
+            .insert_resource(rng)
+            .insert_resource(self.walls.clone())
+            .insert_resource(self.bounds)
+            .init_resource::<RunOutcome>()
+            .add_state::<AppState>()
+            .insert_resource(AmbientLight {
+                brightness: 1.,
+                ..default()
+            })
+            .insert_resource(ClearColor(Color::rgb(0.04, 0.04, 0.1)))
+            .add_systems(
+                Startup,
+                (load_meshes, setup_window, setup_camera, setup_light),
+            )
+            .add_systems(PreStartup, apply_grid_anchor)
+            .add_systems(
+                OnTransition {
+                    from: AppState::MainMenu,
+                    to: AppState::Playing,
+                },
+                run_start_run_schedule,
+            )
+            .add_systems(
+                OnTransition {
+                    from: AppState::GameOver,
+                    to: AppState::Playing,
+                },
+                run_start_run_schedule,
+            )
+            .add_systems(
+                Update,
+                (
+                    (check_spawn_transforms, position_translation).chain(),
+                    apply_turn_actions.run_if(in_state(AppState::Playing)),
+                    rotate_head,
+                    update_danger_sphere,
+                    tick_spawn_ring_flash,
+                    (
+                        sync_graphics_preset,
+                        apply_graphics_settings,
+                        update_segment_meshes.run_if(resource_changed::<GraphicsSettings>()),
+                    )
+                        .chain(),
+                    difficulty_scaling,
+                    // a death in the winning tick takes precedence
+                    (handle_victory, handle_death_event).chain(),
+                    restart_system.run_if(in_state(AppState::GameOver)),
+                    desaturate_slow_motion,
+                ),
+            )
+            // nested, a plugin tuple holds at most 15 plugins
+            .add_plugins((
+                TickPlugin,
+                (
+                    actions::ActionsPlugin,
+                    assist::AssistPlugin,
+                    board_hash::BoardHashPlugin,
+                    death_log::DeathLogPlugin,
+                    floor::FloorPlugin,
+                    food_timer::FoodTimerPlugin,
+                    game_over::GameOverPlugin,
+                    hud::HudPlugin,
+                    locks::LocksPlugin,
+                    menu::MenuPlugin,
+                ),
+                (
+                    milestones::MilestonesPlugin,
+                    pause::PausePlugin,
+                    persistence::PersistencePlugin,
+                    picking::PickingPlugin,
+                    quit::QuitPlugin,
+                    self_check::SelfCheckPlugin,
+                    sounds::SoundsPlugin,
+                    tooltip::TooltipPlugin,
+                    walls::WallsPlugin,
+                    zoom::ZoomPlugin,
+                ),
+            ));
+
+        #[cfg(debug_assertions)]
+        app.add_plugins(console::ConsolePlugin);
+
+        #[cfg(feature = "scripting")]
+        app.add_plugins(scripting::ScriptingPlugin);
+
+        if self.validate {
+            app.add_plugins(validate::ValidatePlugin);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    #[test]
+    fn build_info_is_filled_in() {
+        let build_info = BuildInfo::default();
+        assert!(!build_info.version.is_empty());
+        assert!(!build_info.git_hash.is_empty());
+        assert_eq!(
+            build_info.to_string(),
+            format!("v{} ({})", build_info.version, build_info.git_hash)
+        );
+    }
+
+    /// World with a main camera and what `apply_graphics_settings` reads
+    fn graphics_world(settings: GraphicsSettings) -> (World, Entity) {
+        let mut world = World::new();
+        world.insert_resource(settings);
+        world.init_resource::<Msaa>();
+        world.init_resource::<ClearColor>();
+        world.init_resource::<Assets<Mesh>>();
+        world.init_resource::<Assets<StandardMaterial>>();
+        world.run_system_once(load_meshes);
+        let camera = world.spawn(MainCamera).id();
+        (world, camera)
+    }
+
+    #[test]
+    fn bloom_is_added_to_and_removed_from_the_camera() {
+        let (mut world, camera) = graphics_world(GraphicsSettings::default());
+        world.run_system_once(apply_graphics_settings);
+        let bloom = world.get::<BloomSettings>(camera).map(|b| b.intensity);
+        assert_eq!(bloom, Some(0.15));
+
+        world.resource_mut::<GraphicsSettings>().bloom_intensity = 0.3;
+        world.run_system_once(apply_graphics_settings);
+        let bloom = world.get::<BloomSettings>(camera).map(|b| b.intensity);
+        assert_eq!(bloom, Some(0.3));
+
+        world.resource_mut::<GraphicsSettings>().bloom = false;
+        world.run_system_once(apply_graphics_settings);
+        assert!(world.get::<BloomSettings>(camera).is_none());
+    }
+
+    #[test]
+    fn glow_is_turned_off_and_on_live() {
+        let (mut world, _) = graphics_world(GraphicsPreset::Low.settings().unwrap());
+        world.run_system_once(apply_graphics_settings);
+        let emissive = |world: &World| {
+            let handle = &world.resource::<SnakeAssets>().golden_material;
+            world
+                .resource::<Assets<StandardMaterial>>()
+                .get(handle)
+                .unwrap()
+                .emissive
+        };
+        assert_eq!(emissive(&world), Color::BLACK);
+
+        world.resource_mut::<GraphicsSettings>().glow = true;
+        world.run_system_once(apply_graphics_settings);
+        assert_eq!(emissive(&world), GOLDEN_EMISSIVE);
+    }
+
+    #[test]
+    fn presets_scale_up_from_low_to_high() {
+        let low = GraphicsPreset::Low.settings().unwrap();
+        assert!(!low.shadows && !low.bloom && !low.fog && !low.msaa);
+        assert!(low.classic_meshes && !low.glow && !low.particles);
+
+        let medium = GraphicsPreset::Medium.settings().unwrap();
+        assert!(medium.shadows && !medium.bloom && !medium.fog && medium.msaa);
+        assert!(!medium.classic_meshes && medium.glow && medium.particles);
+
+        let high = GraphicsPreset::High.settings().unwrap();
+        assert!(high.shadows && high.bloom && high.fog && high.msaa);
+        assert!(!high.classic_meshes && high.glow && high.particles);
+
+        assert_eq!(GraphicsPreset::Custom.settings(), None);
+        assert_eq!(GraphicsSettings::default(), high);
+    }
+
+    #[test]
+    fn picking_a_preset_applies_it_and_editing_an_option_makes_it_custom() {
+        let mut world = World::new();
+        world.init_resource::<GraphicsSettings>();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(sync_graphics_preset);
+        schedule.run(&mut world);
+
+        world.resource_mut::<GraphicsSettings>().preset = GraphicsPreset::Low;
+        schedule.run(&mut world);
+        assert_eq!(
+            *world.resource::<GraphicsSettings>(),
+            GraphicsPreset::Low.settings().unwrap()
+        );
+
+        world.resource_mut::<GraphicsSettings>().fog = true;
+        schedule.run(&mut world);
+        let settings = world.resource::<GraphicsSettings>();
+        assert_eq!(settings.preset, GraphicsPreset::Custom);
+        assert!(settings.fog);
+    }
+
+    /// World with a snake of `length` segments whose head sits on the cell
+    /// the tail tip just vacated
+    fn ouroboros_world(length: usize) -> World {
+        let mut world = World::new();
+        world.insert_resource(OuroborosRule(true));
+        world.init_resource::<Events<GameWonEvent>>();
+
+        world.spawn((
+            SnakeHead(Direction::Up),
+            SnakeSegment,
+            Position(IVec3::ZERO),
+        ));
+        for y in 1..length - 1 {
+            world.spawn((SnakeSegment, Position(IVec3::new(1, y as i32, 0))));
+        }
+        world.spawn((
+            SnakeSegment,
+            Position(IVec3::new(1, 0, 0)),
+            LastSnakeSegment(Some(Position(IVec3::ZERO))),
+        ));
+        world
+    }
+
+    fn won(world: &World) -> bool {
+        !world.resource::<Events<GameWonEvent>>().is_empty()
+    }
+
+    #[test]
+    fn biting_the_tail_wins_only_from_the_minimum_length() {
+        let mut world = ouroboros_world(OUROBOROS_MIN_LENGTH - 1);
+        world.run_system_once(check_ouroboros);
+        assert!(!won(&world));
+
+        let mut world = ouroboros_world(OUROBOROS_MIN_LENGTH);
+        world.run_system_once(check_ouroboros);
+        assert!(won(&world));
+
+        let mut world = ouroboros_world(OUROBOROS_MIN_LENGTH);
+        world.insert_resource(OuroborosRule(false));
+        world.run_system_once(check_ouroboros);
+        assert!(!won(&world));
+    }
+
+    /// World with what the run ending handlers read
+    fn run_end_world() -> World {
+        let mut world = World::new();
+        world.insert_resource(State::new(AppState::Playing));
+        world.init_resource::<NextState<AppState>>();
+        world.init_resource::<Events<GameWonEvent>>();
+        world.init_resource::<Events<DeathEvent>>();
+        world.init_resource::<RunOutcome>();
+        world.init_resource::<TickTimer>();
+        world.init_resource::<Score>();
+        world.init_resource::<FoodCounters>();
+        world
+    }
+
+    fn run_end_handlers(world: &mut World) {
+        let mut schedule = Schedule::default();
+        schedule.add_systems((handle_victory, handle_death_event).chain());
+        schedule.run(world);
+    }
+
+    #[test]
+    fn winning_ends_the_run() {
+        let mut world = run_end_world();
+        world.send_event(GameWonEvent {
+            cause: WinCause::Ouroboros,
+        });
+        run_end_handlers(&mut world);
+
+        assert_eq!(
+            world.resource::<NextState<AppState>>().0,
+            Some(AppState::GameOver)
+        );
+        assert_eq!(*world.resource::<RunOutcome>(), RunOutcome::Won);
+    }
+
+    #[test]
+    fn dying_in_the_winning_tick_loses() {
+        let mut world = run_end_world();
+        world.send_event(GameWonEvent {
+            cause: WinCause::Ouroboros,
+        });
+        world.send_event(DeathEvent {
+            reason: DeathReason::SelfCollision,
+        });
+        run_end_handlers(&mut world);
+
+        assert_eq!(
+            world.resource::<NextState<AppState>>().0,
+            Some(AppState::GameOver)
+        );
+        assert_eq!(*world.resource::<RunOutcome>(), RunOutcome::Lost);
+    }
+
+    /// Ticks run and discarded after a frame of 16 ms, then a 10 s hitch
+    fn ticks_after_a_hitch(max_ticks_per_frame: u32) -> (u64, u64) {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TickTimer {
+                interval: Duration::from_millis(100),
+                max_ticks_per_frame,
+                ..default()
+            })
+            .init_resource::<SlowMotion>()
+            .insert_resource(bevy::time::TimeUpdateStrategy::ManualDuration(
+                Duration::from_millis(16),
+            ))
+            .add_systems(
+                First,
+                (apply_tick_interval, discard_ticks)
+                    .chain()
+                    .after(TimeSystem),
+            )
+            .add_systems(FixedUpdate, count_tick);
+        app.update();
+
+        app.insert_resource(bevy::time::TimeUpdateStrategy::ManualDuration(
+            Duration::from_secs(10),
+        ));
+        app.update();
+
+        let tick = app.world.resource::<TickTimer>();
+        assert_eq!(tick.discarded_time, Duration::ZERO);
+        (tick.count, tick.discarded)
+    }
+
+    #[test]
+    fn hitches_run_a_single_tick_and_discard_the_rest() {
+        assert_eq!(ticks_after_a_hitch(1), (1, 99));
+    }
+
+    #[test]
+    fn hitches_run_at_most_the_configured_ticks() {
+        assert_eq!(ticks_after_a_hitch(3), (3, 97));
+    }
+
+    #[test]
+    fn discarded_time_adds_up_across_frames() {
+        let mut tick = TickTimer::default();
+        let interval = Duration::from_millis(200);
+
+        tick.discard(Duration::from_millis(150), interval);
+        assert_eq!(tick.discarded, 0);
+
+        tick.discard(Duration::from_millis(450), interval);
+        assert_eq!(tick.discarded, 3);
+        assert_eq!(tick.discarded_time, Duration::ZERO);
+
+        tick.discard(Duration::from_millis(150), Duration::ZERO);
+        assert_eq!(tick.discarded, 3);
+    }
+
+    #[test]
+    fn scoring_keeps_an_interval_set_by_hand() {
+        let mut app = tick_app(0);
+        let interval = Duration::from_millis(400);
+        app.world.resource_mut::<TickTimer>().set_override(interval);
+
+        app.world.resource_mut::<Score>().0 = 5;
+        app.world.run_system_once(difficulty_scaling);
+        assert_eq!(app.world.resource::<TickTimer>().interval, interval);
+
+        // a new run scales from the start interval again
+        app.world.run_schedule(StartRun);
+        app.world.resource_mut::<Score>().0 = 5;
+        app.world.run_system_once(difficulty_scaling);
+        let tick = app.world.resource::<TickTimer>();
+        assert_eq!(tick.override_interval, None);
+        assert_eq!(
+            tick.interval,
+            tick.start_interval - tick.speed_up_per_point * 5
+        );
+    }
+
+    /// Headless app laid out for a run with `seed`, whose gameplay ticks
+    /// are run by hand with [`run_tick`] so no clock is involved
+    fn tick_app(seed: u64) -> App {
+        tick_app_with(seed, |_| {})
+    }
+
+    /// Like [`tick_app`], with `setup` changing the resources before the
+    /// board is laid out
+    fn tick_app_with(seed: u64, setup: impl FnOnce(&mut App)) -> App {
+        let mut app = App::new();
+        app.add_state::<AppState>()
+            .init_resource::<Assets<Mesh>>()
+            .init_resource::<Assets<StandardMaterial>>()
+            .init_resource::<GridConfig>()
+            .init_resource::<GridBounds>()
+            .insert_resource(GameRng::seeded(seed))
+            .add_plugins((
+                TickPlugin,
+                food_timer::FoodTimerPlugin,
+                locks::LocksPlugin,
+                validate::ValidatePlugin,
+                walls::WallsPlugin,
+                board_hash::BoardHashPlugin,
+            ))
+            .add_systems(Startup, load_meshes);
+        setup(&mut app);
+
+        // either schedule may be empty, and then it does not exist
+        let _ = app.world.try_run_schedule(Startup);
+        let _ = app.world.try_run_schedule(PostStartup);
+        app.world.insert_resource(State::new(AppState::Playing));
+        app.world.run_schedule(StartRun);
+        app
+    }
+
+    fn run_tick(app: &mut App) {
+        app.world.run_schedule(FixedUpdate);
+    }
+
+    fn snake_length(app: &mut App) -> usize {
+        app.world
+            .query_filtered::<(), With<SnakeSegment>>()
+            .iter(&app.world)
+            .count()
+    }
+
+    fn food_cells(app: &mut App) -> Vec<IVec3> {
+        app.world
+            .query_filtered::<&Position, With<Food>>()
+            .iter(&app.world)
+            .map(|p| p.0)
+            .collect()
+    }
+
+    /// Moves the only food to `cell`
+    fn place_food(app: &mut App, cell: IVec3) {
+        let mut food = app.world.query_filtered::<&mut Position, With<Food>>();
+        food.single_mut(&mut app.world).0 = cell;
+    }
+
+    #[test]
+    fn eating_grows_and_respawns_within_the_tick() {
+        let mut app = tick_app(1);
+        assert_eq!(snake_length(&mut app), 2);
+        assert_eq!(food_cells(&mut app).len(), 1);
+
+        // the head starts at the origin heading up
+        place_food(&mut app, IVec3::Y);
+        run_tick(&mut app);
+
+        assert_eq!(snake_length(&mut app), 3);
+        let food = food_cells(&mut app);
+        assert_eq!(food.len(), 1);
+        assert_ne!(food[0], IVec3::Y);
+        assert_eq!(app.world.resource::<SnakeOrder>().0.len(), 3);
+    }
+
+    fn food_ages(app: &mut App) -> Vec<u32> {
+        app.world
+            .query_filtered::<&food_timer::FoodTimer, With<Food>>()
+            .iter(&app.world)
+            .map(|timer| timer.age_ticks)
+            .collect()
+    }
+
+    #[test]
+    fn stale_food_moves_without_being_eaten() {
+        let mut app = tick_app_with(260, |app| {
+            app.insert_resource(food_timer::FoodLifetime(3));
+        });
+        // out of the way of the head going up
+        let cell = IVec3::new(3, -3, 3);
+        place_food(&mut app, cell);
+
+        run_tick(&mut app);
+        run_tick(&mut app);
+        assert_eq!(food_cells(&mut app), [cell]);
+
+        run_tick(&mut app);
+        let food = food_cells(&mut app);
+        assert_eq!(food.len(), 1);
+        assert_ne!(food[0], cell);
+        let snake: Vec<IVec3> = app
+            .world
+            .query_filtered::<&Position, With<SnakeSegment>>()
+            .iter(&app.world)
+            .map(|p| p.0)
+            .collect();
+        assert!(!snake.contains(&food[0]));
+        assert_eq!(food_ages(&mut app), [0]);
+
+        assert!(app.world.resource::<Events<EatEvent>>().is_empty());
+        assert_eq!(snake_length(&mut app), 2);
+        assert_eq!(app.world.resource::<PendingGrowth>().0, 0);
+        assert_eq!(app.world.resource::<Score>().0, 0);
+    }
+
+    #[test]
+    fn eating_food_before_it_moves_starts_a_fresh_one() {
+        let mut app = tick_app_with(260, |app| {
+            app.insert_resource(food_timer::FoodLifetime(2));
+        });
+        // the head reaches it on the tick it would move
+        place_food(&mut app, IVec3::Y * 2);
+
+        run_tick(&mut app);
+        run_tick(&mut app);
+        assert_eq!(app.world.resource::<Events<EatEvent>>().len(), 1);
+        let food = food_cells(&mut app);
+        assert_eq!(food.len(), 1);
+        assert_eq!(food_ages(&mut app), [0]);
+
+        // the new food keeps its own lifetime
+        run_tick(&mut app);
+        assert_eq!(food_cells(&mut app), food);
+        assert_eq!(food_ages(&mut app), [1]);
+    }
+
+    #[test]
+    fn food_without_a_lifetime_stays_in_place() {
+        let mut app = tick_app_with(260, |app| {
+            app.insert_resource(food_timer::FoodLifetime(0));
+        });
+        let cell = IVec3::new(3, -3, 3);
+        place_food(&mut app, cell);
+
+        // the head going up stays in the arena for five ticks
+        for _ in 0..5 {
+            run_tick(&mut app);
+        }
+        assert_eq!(food_cells(&mut app), [cell]);
+        assert!(app.world.resource::<Events<EatEvent>>().is_empty());
+    }
+
+    #[test]
+    fn food_never_spawns_under_a_long_snake() {
+        let half_extent = IVec3::splat(2);
+        let mut app = tick_app_with(258, |app| {
+            app.insert_resource(GridBounds {
+                half_extent,
+                ..default()
+            });
+        });
+
+        // the snake takes every cell of the arena but the first few
+        let arena: Vec<IVec3> = (-2..=2)
+            .flat_map(|x| (-2..=2).flat_map(move |y| (-2..=2).map(move |z| IVec3::new(x, y, z))))
+            .collect();
+        let (free, taken) = arena.split_at(6);
+        for &cell in taken.iter().filter(|cell| !START_CELLS.contains(cell)) {
+            app.world.spawn((SnakeSegment, Position(cell)));
+        }
+        assert_eq!(snake_length(&mut app), taken.len());
+
+        let mut picked = Vec::new();
+        for _ in 0..1000 {
+            let food: Vec<Entity> = app
+                .world
+                .query_filtered::<Entity, With<Food>>()
+                .iter(&app.world)
+                .collect();
+            for ent in food {
+                app.world.despawn(ent);
+            }
+
+            app.world.run_system_once(food_spawner);
+            let food = food_cells(&mut app);
+            assert_eq!(food.len(), 1);
+            assert!(
+                free.contains(&food[0]),
+                "food spawned under the snake at {}",
+                food[0]
+            );
+            if !picked.contains(&food[0]) {
+                picked.push(food[0]);
+            }
+        }
+        // every free cell is reachable, the pick is not stuck on one
+        assert_eq!(picked.len(), free.len());
+    }
+
+    #[test]
+    fn running_into_a_wall_ends_the_run() {
+        let mut app = tick_app(1);
+        place_food(&mut app, IVec3::new(4, 0, 4));
+        app.world.spawn(walls::WallBundle {
+            position: Position(IVec3::Y * 2),
+            ..default()
+        });
+
+        // the validator runs every tick and accepts the wall
+        run_tick(&mut app);
+        assert!(app.world.resource::<Events<DeathEvent>>().is_empty());
+
+        run_tick(&mut app);
+        let reasons: Vec<_> = app
+            .world
+            .resource::<Events<DeathEvent>>()
+            .iter_current_update_events()
+            .map(|death| death.reason)
+            .collect();
+        assert_eq!(reasons, [DeathReason::HitWall]);
+    }
+
+    /// Spawns a key and lock pair, out of the way of the regular food
+    fn spawn_lock_pair(app: &mut App, key: IVec3, lock: IVec3) {
+        place_food(app, IVec3::new(4, 0, 4));
+        app.world.run_system_once(
+            move |mut commands: Commands,
+                  grid: Res<GridConfig>,
+                  snake_assets: Res<SnakeAssets>,
+                  lock_assets: Res<LockAssets>| {
+                locks::spawn_pair(&mut commands, key, lock, &grid, &snake_assets, &lock_assets);
+            },
+        );
+    }
+
+    fn locked_cells(app: &mut App) -> Vec<IVec3> {
+        app.world
+            .query_filtered::<&Position, With<Locked>>()
+            .iter(&app.world)
+            .map(|p| p.0)
+            .collect()
+    }
+
+    #[test]
+    fn locked_food_is_inert_until_its_key_is_eaten() {
+        let mut app = tick_app(1);
+        // the head starts at the origin heading up, over the lock first
+        spawn_lock_pair(&mut app, IVec3::Y * 2, IVec3::Y);
+
+        run_tick(&mut app);
+        assert_eq!(snake_length(&mut app), 2);
+        assert_eq!(locked_cells(&mut app), [IVec3::Y]);
+
+        run_tick(&mut app);
+        assert_eq!(snake_length(&mut app), 3);
+        assert!(locked_cells(&mut app).is_empty());
+        assert!(food_cells(&mut app).contains(&IVec3::Y));
+
+        assert_eq!(app.world.resource::<Score>().0, FoodKind::Key.points());
+        assert_eq!(app.world.resource::<FoodCounters>().count(FoodKind::Key), 1);
+    }
+
+    #[test]
+    fn unlocked_food_is_worth_extra_points() {
+        let mut app = tick_app(1);
+        spawn_lock_pair(&mut app, IVec3::Y, IVec3::Y * 2);
+
+        run_tick(&mut app);
+        assert!(locked_cells(&mut app).is_empty());
+
+        run_tick(&mut app);
+        assert_eq!(snake_length(&mut app), 4);
+        assert!(!food_cells(&mut app).contains(&(IVec3::Y * 2)));
+
+        let counters = *app.world.resource::<FoodCounters>();
+        assert_eq!(counters.count(FoodKind::Key), 1);
+        assert_eq!(counters.count(FoodKind::Unlocked), 1);
+        assert_eq!(
+            app.world.resource::<Score>().0,
+            FoodKind::Key.points() + locks::UNLOCKED_POINTS
+        );
+    }
+
+    /// Turn for the next tick of `sim`: towards the food without running
+    /// into anything, except for an occasional random one so runs also end
+    fn cross_check_turn(sim: &Simulation, rng: &mut StdRng) -> Direction {
+        if rng.gen_ratio(1, 8) {
+            return DIRECTIONS[rng.gen_range(0..DIRECTIONS.len())];
+        }
+
+        let rules = sim.rules();
+        let body: Vec<IVec3> = sim.body().collect();
+        let food = sim.food().unwrap_or(IVec3::ZERO);
+        DIRECTIONS
+            .into_iter()
+            .map(|direction| {
+                let cell = sim.head() + IVec3::from(direction);
+                if rules.wrap {
+                    (
+                        direction,
+                        grid_math::wrap_to_bounds(cell, rules.half_extent),
+                    )
+                } else {
+                    (direction, cell)
+                }
+            })
+            .filter(|(_, cell)| {
+                grid_math::is_in_bounds(*cell, rules.half_extent)
+                    && !body[1..].contains(cell)
+                    && !sim.walls().contains(cell)
+            })
+            .min_by_key(|(_, cell)| grid_math::manhattan_distance(*cell, food))
+            .map_or(sim.direction(), |(direction, _)| direction)
+    }
+
+    /// Plays the same turns in the app and in a [`Simulation`] with the same
+    /// seed, checking after every tick that both boards hash the same
+    fn cross_check(seed: u64, rules: Rules, walls: &[IVec3]) -> Option<TickOutcome> {
+        let mut app = tick_app_with(seed, |app| {
+            app.insert_resource(GridBounds {
+                half_extent: rules.half_extent,
+                wrap: if rules.wrap {
+                    WrapMode::Wrap
+                } else {
+                    WrapMode::Walls
+                },
+            })
+            .insert_resource(OuroborosRule(rules.ouroboros))
+            .insert_resource(SpawnProtection(rules.spawn_protection))
+            .insert_resource(food_timer::FoodLifetime(rules.food_lifetime))
+            .insert_resource(walls::WallLayout(walls.to_vec()))
+            .insert_resource(rules);
+        });
+        let mut sim = Simulation::with_walls(rules, seed, walls.iter().copied());
+        let mut turns = StdRng::seed_from_u64(seed);
+
+        while sim.tick() < 500 {
+            let turn = cross_check_turn(&sim, &mut turns);
+            let mut head = app.world.query::<&SnakeHead>();
+            let heading = head.single(&app.world).0;
+            app.world
+                .resource_mut::<TurnBuffer>()
+                .request(rules.turn_policy, heading, turn);
+
+            run_tick(&mut app);
+            let outcome = sim.step(Some(turn));
+
+            let board_hash = *app.world.resource::<board_hash::BoardHash>();
+            assert_eq!(board_hash.tick, sim.tick());
+            assert_eq!(
+                board_hash.hash,
+                sim.state_hash(),
+                "seed {seed} diverged at tick {}",
+                sim.tick()
+            );
+            assert_eq!(
+                app.world.resource::<PendingGrowth>().0,
+                sim.pending_growth()
+            );
+            // nothing clears the events here, they stay once sent
+            let died = !app.world.resource::<Events<DeathEvent>>().is_empty();
+            let won = !app.world.resource::<Events<GameWonEvent>>().is_empty();
+            assert_eq!(died, outcome == TickOutcome::Died, "seed {seed}");
+            assert_eq!(won, outcome == TickOutcome::Won, "seed {seed}");
+            if died || won {
+                break;
+            }
+        }
+        sim.ended()
+    }
+
+    #[test]
+    fn the_app_and_the_simulation_play_alike() {
+        let outcomes: Vec<_> = (0..8)
+            .map(|seed| cross_check(seed, Rules::default(), &[]))
+            .collect();
+        // the random turns end most runs, so deaths are compared too
+        assert!(outcomes.contains(&Some(TickOutcome::Died)));
+    }
+
+    #[test]
+    fn the_app_and_the_simulation_play_alike_with_walls_and_wrapping() {
+        let walls: Vec<IVec3> = (-3..=3).map(|x| IVec3::new(x, 2, 0)).collect();
+        let rules = Rules::default()
+            .with_half_extent(IVec3::splat(3))
+            .with_wrap(true)
+            .with_ouroboros(true)
+            .with_food_lifetime(5);
+        for seed in 0..8 {
+            cross_check(seed, rules, &walls);
+        }
+    }
+
+    const DIRECTIONS: [Direction; 6] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+        Direction::Forward,
+        Direction::Backward,
+    ];
+
+    /// World with a snake laid out on `cells`, head first, in `bounds`
+    fn snake_world(bounds: GridBounds, cells: &[IVec3]) -> (World, Vec<Entity>) {
+        let mut world = World::new();
+        world.insert_resource(bounds);
+        let segments: Vec<Entity> = cells
+            .iter()
+            .enumerate()
+            .map(|(index, cell)| {
+                let mut segment =
+                    world.spawn((SnakeSegment, Position(*cell), Transform::default()));
+                if index == 0 {
+                    segment.insert(SnakeHead(Direction::Up));
+                }
+                segment.id()
+            })
+            .collect();
+        world.insert_resource(SnakeOrder(segments.clone()));
+        (world, segments)
+    }
+
+    fn facing(world: &World, segment: Entity) -> Vec3 {
+        world.get::<Transform>(segment).unwrap().rotation * Vec3::Y
+    }
+
+    #[test]
+    fn straight_segments_face_the_way_the_snake_goes() {
+        for direction in DIRECTIONS {
+            let step = IVec3::from(direction);
+            let cells = [step * 2, step, IVec3::ZERO];
+            let (mut world, segments) = snake_world(GridBounds::default(), &cells);
+            world.run_system_once(orient_segments);
+
+            for &segment in &segments[1..] {
+                let facing = facing(&world, segment);
+                assert!(
+                    facing.abs_diff_eq(Vec3::from(direction), 1e-5),
+                    "{direction:?} segment faces {facing}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn segments_follow_the_body_around_a_turn() {
+        let cells = [
+            IVec3::new(1, 1, 0),
+            IVec3::new(0, 1, 0),
+            IVec3::ZERO,
+            IVec3::NEG_Y,
+        ];
+        let (mut world, segments) = snake_world(GridBounds::default(), &cells);
+        world.run_system_once(orient_segments);
+
+        assert!(facing(&world, segments[1]).abs_diff_eq(Vec3::X, 1e-5));
+        assert!(facing(&world, segments[2]).abs_diff_eq(Vec3::Y, 1e-5));
+        assert!(facing(&world, segments[3]).abs_diff_eq(Vec3::Y, 1e-5));
+
+        // the snake moves on, the corner moves down the body
+        let moved = [IVec3::new(2, 1, 0), cells[0], cells[1], cells[2]];
+        for (segment, cell) in segments.iter().zip(moved) {
+            world.get_mut::<Position>(*segment).unwrap().0 = cell;
+        }
+        world.run_system_once(orient_segments);
+
+        assert!(facing(&world, segments[1]).abs_diff_eq(Vec3::X, 1e-5));
+        assert!(facing(&world, segments[2]).abs_diff_eq(Vec3::X, 1e-5));
+        assert!(facing(&world, segments[3]).abs_diff_eq(Vec3::Y, 1e-5));
+    }
+
+    #[test]
+    fn segments_across_a_wrapped_face_face_the_face() {
+        let bounds = GridBounds {
+            half_extent: IVec3::splat(2),
+            wrap: WrapMode::Wrap,
+        };
+        let cells = [
+            IVec3::new(0, -2, 0),
+            IVec3::new(0, 2, 0),
+            IVec3::new(0, 1, 0),
+        ];
+        let (mut world, segments) = snake_world(bounds, &cells);
+        world.run_system_once(orient_segments);
+
+        assert!(facing(&world, segments[1]).abs_diff_eq(Vec3::Y, 1e-5));
+        assert!(facing(&world, segments[2]).abs_diff_eq(Vec3::Y, 1e-5));
+    }
+
+    #[test]
+    fn spawn_rings_fade_through_every_step() {
+        assert_eq!(spawn_ring_fade_step(0.0), 0);
+        assert_eq!(spawn_ring_fade_step(0.2), 1);
+        assert_eq!(spawn_ring_fade_step(0.99), SPAWN_RING_FADE_STEPS - 1);
+        assert_eq!(spawn_ring_fade_step(1.0), SPAWN_RING_FADE_STEPS - 1);
+    }
+
+    #[test]
+    fn spawn_rings_share_their_materials() {
+        let mut app = tick_app(0);
+        let materials = app.world.resource::<Assets<StandardMaterial>>().len();
+        for _ in 0..5 {
+            place_food(&mut app, IVec3::Y * (snake_length(&mut app) as i32 - 1));
+            run_tick(&mut app);
+        }
+        assert_eq!(snake_length(&mut app), 7);
+        assert_eq!(
+            app.world.resource::<Assets<StandardMaterial>>().len(),
+            materials
+        );
+    }
+
+    #[test]
+    fn the_low_preset_skips_spawn_rings_and_corners() {
+        let mut app = tick_app_with(0, |app| {
+            app.insert_resource(GraphicsPreset::Low.settings().unwrap());
+        });
+        // up, then right, so the neck bends
+        place_food(&mut app, IVec3::Y);
+        run_tick(&mut app);
+        app.world.resource_mut::<TurnBuffer>().request(
+            TurnPolicy::LastWins,
+            Direction::Up,
+            Direction::Right,
+        );
+        run_tick(&mut app);
+
+        let rings = app
+            .world
+            .query_filtered::<(), With<SpawnRingFlash>>()
+            .iter(&app.world)
+            .count();
+        assert_eq!(rings, 0);
+
+        let neck = app.world.resource::<SnakeOrder>().0[1];
+        let tail_mesh = app.world.resource::<SnakeAssets>().tail_mesh.clone();
+        assert_eq!(app.world.get::<Handle<Mesh>>(neck), Some(&tail_mesh));
+    }
+
+    #[test]
+    fn steps_stick_at_the_coordinate_limits() {
+        let bounds = GridBounds {
+            half_extent: IVec3::splat(i32::MAX),
+            wrap: WrapMode::Walls,
+        };
+        let corner = IVec3::new(i32::MAX, i32::MIN, 0);
+        assert_eq!(bounds.step(corner, Direction::Right), corner);
+        assert_eq!(bounds.step(corner, Direction::Down), corner);
+        assert_eq!(
+            bounds.step(corner, Direction::Left),
+            IVec3::new(i32::MAX - 1, i32::MIN, 0)
+        );
+    }
+
+    #[test]
+    fn segments_apart_keep_their_rotation() {
+        let cells = [IVec3::ZERO, IVec3::new(0, -3, 0)];
+        let (mut world, segments) = snake_world(GridBounds::default(), &cells);
+        world.run_system_once(orient_segments);
+
+        assert_eq!(
+            world.get::<Transform>(segments[1]).unwrap().rotation,
+            Quat::IDENTITY
+        );
+    }
+
+    const FOOD_KINDS: [FoodKind; 5] = [
+        FoodKind::Regular,
+        FoodKind::SlowMotion,
+        FoodKind::Key,
+        FoodKind::Unlocked,
+        FoodKind::Golden,
+    ];
+
+    #[test]
+    fn every_kind_has_its_own_counter() {
+        for kind in FOOD_KINDS {
+            let mut counters = FoodCounters::default();
+            counters.record(kind);
+            counters.record(kind);
+            for other in FOOD_KINDS {
+                let expected = if other == kind { 2 } else { 0 };
+                assert_eq!(
+                    counters.count(other),
+                    expected,
+                    "{kind:?} counted as {other:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn counters_count_foods_while_the_score_counts_points() {
+        let mut world = World::new();
+        world.init_resource::<Score>();
+        world.init_resource::<FoodCounters>();
+        world.init_resource::<Events<EatEvent>>();
+        for kind in [FoodKind::Golden, FoodKind::Regular, FoodKind::Golden] {
+            world.send_event(EatEvent { kind });
+        }
+        world.run_system_once(update_score);
+
+        let counters = *world.resource::<FoodCounters>();
+        assert_eq!(counters.count(FoodKind::Golden), 2);
+        assert_eq!(counters.count(FoodKind::Regular), 1);
+        assert_eq!(world.resource::<Score>().0, 2 * GOLDEN_POINTS + 1);
+        assert_eq!(
+            counters.to_string(),
+            "1 regular, 0 slow motion, 0 keys, 0 unlocked, 2 golden"
+        );
+    }
+
+    #[test]
+    fn counters_reset_with_the_run() {
+        let mut app = tick_app(0);
+        app.world
+            .resource_mut::<FoodCounters>()
+            .record(FoodKind::Key);
+        app.world.run_schedule(StartRun);
+        assert_eq!(
+            *app.world.resource::<FoodCounters>(),
+            FoodCounters::default()
+        );
+    }
+}
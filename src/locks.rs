@@ -0,0 +1,148 @@
+//! Key and lock food pairs, an optional puzzle rule
+//!
+//! With [`KeysAndLocks`] on, a respawning food is sometimes replaced by a
+//! caged food and a key elsewhere in the arena. The caged food is
+//! inert until the key is eaten, then its cage drops and it is worth
+//! [`UNLOCKED_POINTS`].
+
+use bevy::prelude::*;
+
+pub use crate::simulation::UNLOCKED_POINTS;
+
+use crate::{Food, FoodBundle, GridConfig, Position, SnakeAssets};
+
+/// Chance for a respawn to be a key and lock pair
+pub const LOCK_CHANCE: f64 = 0.15;
+
+/// Whether key and lock pairs may spawn, off by default
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub struct KeysAndLocks(pub bool);
+
+/// Tag for a food that cannot be eaten until its key is
+#[derive(Component, Debug, Default)]
+pub struct Locked;
+
+/// Tag for a food whose key has been eaten
+#[derive(Component, Debug, Default)]
+pub struct Unlocked;
+
+/// Marks a food as the key to the locked food it points to
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyFor(pub Entity);
+
+/// Tag for the cage drawn around a locked food
+#[derive(Component, Debug, Default)]
+struct Cage;
+
+/// Meshes and materials of the pairs
+#[derive(Resource)]
+pub struct LockAssets {
+    key_material: Handle<StandardMaterial>,
+    cage_mesh: Handle<Mesh>,
+    cage_material: Handle<StandardMaterial>,
+}
+
+pub struct LocksPlugin;
+
+impl Plugin for LocksPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<KeysAndLocks>()
+            .init_resource::<KeysAndLocks>()
+            .add_systems(Startup, load_lock_assets)
+            .add_systems(
+                FixedUpdate,
+                release_orphaned_locks.in_set(crate::TickSet::PostTick),
+            );
+    }
+}
+
+fn load_lock_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(LockAssets {
+        key_material: materials.add(StandardMaterial {
+            base_color: Color::rgb(1., 0.8, 0.1),
+            emissive: Color::rgb_linear(1.5, 1.1, 0.1),
+            ..default()
+        }),
+        cage_mesh: meshes.add(Mesh::from(shape::Cube { size: 0.95 })),
+        cage_material: materials.add(StandardMaterial {
+            base_color: Color::rgba(0.7, 0.7, 0.8, 0.35),
+            alpha_mode: AlphaMode::Blend,
+            ..default()
+        }),
+    });
+}
+
+/// Spawns a caged food at `lock` and its key at `key`
+pub fn spawn_pair(
+    commands: &mut Commands,
+    key: IVec3,
+    lock: IVec3,
+    grid: &GridConfig,
+    snake_assets: &SnakeAssets,
+    lock_assets: &LockAssets,
+) {
+    let locked = commands
+        .spawn((
+            FoodBundle {
+                position: Position(lock),
+                mesh: snake_assets.food_mesh.clone(),
+                material: snake_assets.food_material.clone(),
+                transform: grid.cell_transform(lock),
+                ..default()
+            },
+            Locked,
+        ))
+        .with_children(|food| {
+            food.spawn((
+                PbrBundle {
+                    mesh: lock_assets.cage_mesh.clone(),
+                    material: lock_assets.cage_material.clone(),
+                    ..default()
+                },
+                Cage,
+                Name::new("Cage"),
+            ));
+        })
+        .id();
+
+    commands.spawn((
+        FoodBundle {
+            position: Position(key),
+            mesh: snake_assets.food_mesh.clone(),
+            material: lock_assets.key_material.clone(),
+            transform: grid.cell_transform(key),
+            ..default()
+        },
+        KeyFor(locked),
+    ));
+    debug!(target: "rsnake3d::spawn_pair", ?key, ?lock);
+}
+
+/// Makes a locked food edible and drops its cage
+pub fn unlock(commands: &mut Commands, locked: Entity) {
+    commands
+        .entity(locked)
+        .remove::<Locked>()
+        .insert(Unlocked)
+        .despawn_descendants();
+}
+
+/// Unlocks foods whose key is gone without being eaten, so a pair can never
+/// leave the board without an edible food
+fn release_orphaned_locks(
+    mut commands: Commands,
+    locked: Query<Entity, (With<Food>, With<Locked>)>,
+    keys: Query<&KeyFor, With<Food>>,
+) {
+    for ent in locked.iter() {
+        if !keys.iter().any(|KeyFor(target)| *target == ent) {
+            debug!(target: "rsnake3d::release_orphaned_locks", ?ent);
+            unlock(&mut commands, ent);
+        }
+    }
+}
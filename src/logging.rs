@@ -0,0 +1,83 @@
+//! Tracing setup driven by the command line and `RUST_LOG`
+
+use std::{fs::File, path::PathBuf, sync::Mutex};
+
+use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+
+/// Noisy render crates are kept quiet unless asked for explicitly
+const DEFAULT_DIRECTIVES: &str = "wgpu=error,naga=warn";
+
+/// Logging options read from the command line
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LogOptions {
+    /// `--log-level <filter>`, takes precedence over `RUST_LOG`
+    pub level: Option<String>,
+    /// `--log-file <path>`, receives JSON formatted events
+    pub file: Option<PathBuf>,
+}
+
+impl LogOptions {
+    /// Picks the logging flags out of the process arguments, leaving the rest alone
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let mut options = LogOptions::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+
+            match flag.as_str() {
+                "--log-level" => options.level = inline_value.or_else(|| args.next()),
+                "--log-file" => {
+                    options.file = inline_value.or_else(|| args.next()).map(PathBuf::from)
+                }
+                _ => {}
+            }
+        }
+
+        options
+    }
+
+    fn filter(&self) -> EnvFilter {
+        let directives = match &self.level {
+            Some(level) => level.clone(),
+            None => std::env::var(EnvFilter::DEFAULT_ENV).unwrap_or_else(|_| "info".to_string()),
+        };
+
+        EnvFilter::try_new(format!("{directives},{DEFAULT_DIRECTIVES}")).unwrap_or_else(|err| {
+            eprintln!("invalid log filter {directives:?}: {err}, falling back to info");
+            EnvFilter::new(format!("info,{DEFAULT_DIRECTIVES}"))
+        })
+    }
+}
+
+/// Installs the global subscriber, replacing Bevy's `LogPlugin`
+pub fn init(options: &LogOptions) {
+    let json_layer = options
+        .file
+        .as_ref()
+        .and_then(|path| match File::create(path) {
+            Ok(file) => Some(
+                fmt::layer()
+                    .json()
+                    .with_ansi(false)
+                    .with_writer(Mutex::new(file)),
+            ),
+            Err(err) => {
+                eprintln!("could not open log file {}: {err}", path.display());
+                None
+            }
+        });
+
+    let result = tracing_subscriber::registry()
+        .with(options.filter())
+        .with(fmt::layer())
+        .with(json_layer)
+        .try_init();
+
+    if let Err(err) = result {
+        eprintln!("could not set up logging: {err}");
+    }
+}
@@ -0,0 +1,334 @@
+//! Title screen shown before the first run
+//!
+//! The arena stays empty behind the menu until the player picks Play, the
+//! run then begins and `setup_scene` lays out the board. Buttons respond to
+//! the mouse as well as to [`PlayerAction::Navigate`] and
+//! [`PlayerAction::Confirm`], so the menu works from a keyboard or gamepad.
+//! The settings page toggles the rules that have to be chosen before a run.
+//! The build version sits in a corner of the main page, release builds
+//! have no other place showing it.
+
+use bevy::{app::AppExit, prelude::*};
+
+use crate::{
+    actions::{ActionState, PlayerAction, ScreenTurn},
+    hud::UiAssets,
+    locks::KeysAndLocks,
+    persistence::HighScore,
+    AppState, BuildInfo, GridBounds, ReducedMotion, WrapMode,
+};
+
+/// Background of a button that is not highlighted
+const BUTTON_COLOR: Color = Color::rgba(0.15, 0.15, 0.15, 0.8);
+
+/// Background of the highlighted button
+const SELECTED_BUTTON_COLOR: Color = Color::rgba(0.25, 0.55, 0.3, 0.9);
+
+/// Tag for the root node of the menu
+#[derive(Component, Debug, Default)]
+struct MainMenu;
+
+/// Tag for the build version in the corner of the main page
+#[derive(Component, Debug, Default)]
+struct BuildFooter;
+
+/// Page of the menu on screen
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum MenuPage {
+    #[default]
+    Main,
+    Settings,
+}
+
+impl MenuPage {
+    /// Buttons of the page, top to bottom
+    fn buttons(self) -> &'static [MenuButton] {
+        match self {
+            MenuPage::Main => &[MenuButton::Play, MenuButton::Settings, MenuButton::Quit],
+            MenuPage::Settings => &[
+                MenuButton::ReducedMotion,
+                MenuButton::WrapAround,
+                MenuButton::KeysAndLocks,
+                MenuButton::Back,
+            ],
+        }
+    }
+}
+
+/// Index of the highlighted button in [`MenuPage::buttons`]
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct MenuSelection(usize);
+
+/// What a button does once activated
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum MenuButton {
+    Play,
+    Settings,
+    Quit,
+    ReducedMotion,
+    WrapAround,
+    KeysAndLocks,
+    Back,
+}
+
+/// Settings shown and changed by the menu
+#[derive(bevy::ecs::system::SystemParam)]
+struct MenuSettings<'w> {
+    reduced_motion: ResMut<'w, ReducedMotion>,
+    bounds: ResMut<'w, GridBounds>,
+    keys_and_locks: ResMut<'w, KeysAndLocks>,
+}
+
+impl MenuSettings<'_> {
+    fn label(&self, button: MenuButton) -> String {
+        let on_off = |on: bool| if on { "On" } else { "Off" };
+        match button {
+            MenuButton::Play => "Play".into(),
+            MenuButton::Settings => "Settings".into(),
+            MenuButton::Quit => "Quit".into(),
+            MenuButton::ReducedMotion => {
+                format!("Reduced motion: {}", on_off(self.reduced_motion.0))
+            }
+            MenuButton::WrapAround => {
+                format!(
+                    "Wrap around: {}",
+                    on_off(self.bounds.wrap == WrapMode::Wrap)
+                )
+            }
+            MenuButton::KeysAndLocks => {
+                format!("Keys and locks: {}", on_off(self.keys_and_locks.0))
+            }
+            MenuButton::Back => "Back".into(),
+        }
+    }
+}
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MenuPage>()
+            .init_resource::<MenuSelection>()
+            .add_systems(OnEnter(AppState::MainMenu), open_main_menu)
+            .add_systems(OnExit(AppState::MainMenu), despawn_main_menu)
+            .add_systems(
+                Update,
+                (
+                    navigate_menu,
+                    spawn_main_menu.run_if(resource_changed::<MenuPage>()),
+                    highlight_selected_button,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::MainMenu)),
+            );
+    }
+}
+
+fn open_main_menu(mut page: ResMut<MenuPage>) {
+    *page = MenuPage::Main;
+}
+
+/// Builds the current page, replacing the one on screen
+fn spawn_main_menu(
+    mut commands: Commands,
+    ui_assets: Res<UiAssets>,
+    (high_score, build_info): (Res<HighScore>, Res<BuildInfo>),
+    page: Res<MenuPage>,
+    selection: Res<MenuSelection>,
+    settings: MenuSettings,
+    menu: Query<Entity, With<MainMenu>>,
+) {
+    for ent in menu.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+
+    let text_style = |font_size| TextStyle {
+        font: ui_assets.font.clone(),
+        font_size,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(16.0),
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.4).into(),
+                ..default()
+            },
+            MainMenu,
+            Name::new("Main Menu"),
+        ))
+        .with_children(|menu| {
+            let title = match *page {
+                MenuPage::Main => "rsnake3d",
+                MenuPage::Settings => "Settings",
+            };
+            menu.spawn(TextBundle::from_section(title, text_style(64.0)));
+            if *page == MenuPage::Main {
+                menu.spawn(TextBundle::from_section(
+                    format!("Best {}", crate::hud::format_score(high_score.0)),
+                    text_style(32.0),
+                ));
+                menu.spawn((
+                    TextBundle::from_section(
+                        build_info.to_string(),
+                        TextStyle {
+                            color: Color::GRAY,
+                            ..text_style(14.0)
+                        },
+                    )
+                    .with_style(Style {
+                        position_type: PositionType::Absolute,
+                        bottom: Val::Px(8.0),
+                        right: Val::Px(8.0),
+                        ..default()
+                    }),
+                    BuildFooter,
+                ));
+            }
+
+            for (index, &button) in page.buttons().iter().enumerate() {
+                let background = if index == selection.0 {
+                    SELECTED_BUTTON_COLOR
+                } else {
+                    BUTTON_COLOR
+                };
+                menu.spawn((
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Px(320.0),
+                            padding: UiRect::all(Val::Px(8.0)),
+                            justify_content: JustifyContent::Center,
+                            ..default()
+                        },
+                        background_color: background.into(),
+                        ..default()
+                    },
+                    button,
+                ))
+                .with_children(|button_node| {
+                    button_node.spawn(TextBundle::from_section(
+                        settings.label(button),
+                        text_style(28.0),
+                    ));
+                });
+            }
+        });
+}
+
+fn despawn_main_menu(mut commands: Commands, menu: Query<Entity, With<MainMenu>>) {
+    for ent in menu.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+}
+
+/// Moves the highlight and activates buttons, from the mouse or from actions
+fn navigate_menu(
+    actions: Res<ActionState>,
+    interactions: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    mut page: ResMut<MenuPage>,
+    mut selection: ResMut<MenuSelection>,
+    mut settings: MenuSettings,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let buttons = page.buttons();
+    let mut activated = None;
+
+    for (interaction, button) in interactions.iter() {
+        let Some(index) = buttons.iter().position(|b| b == button) else {
+            continue;
+        };
+        match interaction {
+            Interaction::Hovered => selection.0 = index,
+            Interaction::Pressed => {
+                selection.0 = index;
+                activated = Some(*button);
+            }
+            Interaction::None => {}
+        }
+    }
+
+    if actions.pressed(PlayerAction::Navigate(ScreenTurn::Up)) {
+        selection.0 = (selection.0 + buttons.len() - 1) % buttons.len();
+    }
+    if actions.pressed(PlayerAction::Navigate(ScreenTurn::Down)) {
+        selection.0 = (selection.0 + 1) % buttons.len();
+    }
+    if actions.pressed(PlayerAction::Confirm) || actions.pressed(PlayerAction::Restart) {
+        activated = buttons.get(selection.0).copied();
+    }
+    if *page == MenuPage::Settings
+        && (actions.pressed(PlayerAction::Cancel) || actions.pressed(PlayerAction::Pause))
+    {
+        activated = Some(MenuButton::Back);
+    }
+
+    let Some(button) = activated else {
+        return;
+    };
+    debug!(target: "rsnake3d::navigate_menu", ?button);
+
+    match button {
+        MenuButton::Play => {
+            info!(target: "rsnake3d::navigate_menu", "Starting run");
+            next_state.set(AppState::Playing);
+        }
+        MenuButton::Settings => {
+            *page = MenuPage::Settings;
+            selection.0 = 0;
+        }
+        MenuButton::Quit => {
+            info!(target: "rsnake3d::navigate_menu", "Quitting");
+            exit.send(AppExit);
+        }
+        MenuButton::ReducedMotion => {
+            settings.reduced_motion.0 = !settings.reduced_motion.0;
+            page.set_changed();
+        }
+        MenuButton::WrapAround => {
+            settings.bounds.wrap = match settings.bounds.wrap {
+                WrapMode::Walls => WrapMode::Wrap,
+                WrapMode::Wrap => WrapMode::Walls,
+            };
+            page.set_changed();
+        }
+        MenuButton::KeysAndLocks => {
+            settings.keys_and_locks.0 = !settings.keys_and_locks.0;
+            page.set_changed();
+        }
+        MenuButton::Back => {
+            let settings_index = MenuPage::Main
+                .buttons()
+                .iter()
+                .position(|b| *b == MenuButton::Settings);
+            *page = MenuPage::Main;
+            selection.0 = settings_index.unwrap_or_default();
+        }
+    }
+}
+
+fn highlight_selected_button(
+    page: Res<MenuPage>,
+    selection: Res<MenuSelection>,
+    mut buttons: Query<(&MenuButton, &mut BackgroundColor)>,
+) {
+    let selected = page.buttons().get(selection.0);
+    for (button, mut background) in buttons.iter_mut() {
+        let color = if Some(button) == selected {
+            SELECTED_BUTTON_COLOR
+        } else {
+            BUTTON_COLOR
+        };
+        background.set_if_neq(color.into());
+    }
+}
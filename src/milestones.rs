@@ -0,0 +1,174 @@
+//! Fanfares for reaching snake length milestones
+
+use std::time::Duration;
+
+use bevy::{
+    audio::{Pitch, PitchBundle},
+    prelude::*,
+};
+
+use crate::{hud::Toast, ReducedMotion, SnakeAssets, SnakeSegment, StartRun};
+
+/// Snake lengths, head included, that are celebrated once per run
+pub const LENGTH_MILESTONES: [usize; 4] = [10, 25, 50, 100];
+
+/// Notes of the fanfare, as frequencies played one after the other
+const FANFARE: [f32; 3] = [523.25, 659.25, 783.99];
+
+/// How long each fanfare note sounds
+const NOTE_DURATION: Duration = Duration::from_millis(110);
+
+/// Seconds the snake glows after a milestone
+const PULSE_SECONDS: f32 = 0.6;
+
+/// Peak emissive added to the snake material at the start of the pulse
+const PULSE_EMISSIVE: Color = Color::rgb_linear(0.2, 1.5, 0.2);
+
+/// Longest the snake has been this run, shrinking does not lower it, so
+/// crossing a milestone again after a shrink stays quiet
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub struct LongestSnake(pub usize);
+
+/// Milestones passed when growing from `previous` to `current` segments
+pub fn crossed_milestones(previous: usize, current: usize) -> impl Iterator<Item = usize> {
+    LENGTH_MILESTONES
+        .into_iter()
+        .filter(move |milestone| (previous + 1..=current).contains(milestone))
+}
+
+/// Countdown of the glow and the fanfare notes still to play
+#[derive(Resource, Debug, Default)]
+struct Fanfare {
+    pulse: Timer,
+    note: Timer,
+    notes_left: usize,
+}
+
+pub struct MilestonesPlugin;
+
+impl Plugin for MilestonesPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<LongestSnake>()
+            .init_resource::<LongestSnake>()
+            .init_resource::<Fanfare>()
+            .add_systems(StartRun, reset_longest_snake)
+            .add_systems(Update, (check_length_milestones, play_fanfare).chain());
+    }
+}
+
+fn reset_longest_snake(mut longest: ResMut<LongestSnake>) {
+    *longest = LongestSnake::default();
+}
+
+fn check_length_milestones(
+    segments: Query<(), With<SnakeSegment>>,
+    mut longest: ResMut<LongestSnake>,
+    mut fanfare: ResMut<Fanfare>,
+    mut toasts: EventWriter<Toast>,
+) {
+    let length = segments.iter().count();
+    if length <= longest.0 {
+        return;
+    }
+
+    if let Some(milestone) = crossed_milestones(longest.0, length).last() {
+        toasts.send(Toast(format!("Length {milestone}!")));
+        *fanfare = Fanfare {
+            pulse: Timer::from_seconds(PULSE_SECONDS, TimerMode::Once),
+            note: Timer::new(NOTE_DURATION, TimerMode::Repeating),
+            notes_left: FANFARE.len(),
+        };
+        info!(target: "rsnake3d::check_length_milestones", milestone);
+    }
+    longest.0 = length;
+}
+
+fn play_fanfare(
+    mut commands: Commands,
+    time: Res<Time>,
+    reduced_motion: Res<ReducedMotion>,
+    snake_assets: Res<SnakeAssets>,
+    mut fanfare: ResMut<Fanfare>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut pitches: ResMut<Assets<Pitch>>,
+) {
+    if fanfare.notes_left > 0
+        && (fanfare.notes_left == FANFARE.len() || fanfare.note.tick(time.delta()).just_finished())
+    {
+        let frequency = FANFARE[FANFARE.len() - fanfare.notes_left];
+        commands.spawn(PitchBundle {
+            source: pitches.add(Pitch::new(frequency, NOTE_DURATION)),
+            settings: PlaybackSettings::DESPAWN,
+        });
+        fanfare.notes_left -= 1;
+    }
+
+    if fanfare.pulse.finished() {
+        return;
+    }
+    fanfare.pulse.tick(time.delta());
+
+    // a steady glow stands in for the fade when motion is reduced
+    let strength = match (reduced_motion.0, fanfare.pulse.finished()) {
+        (_, true) => 0.0,
+        (true, false) => 1.0,
+        (false, false) => fanfare.pulse.percent_left(),
+    };
+    let emissive = PULSE_EMISSIVE * strength;
+    let handle = &snake_assets.snake_material;
+    // get_mut flags the material for a GPU upload, skip it once settled
+    match materials.get(handle) {
+        Some(material) if material.emissive != emissive => {}
+        _ => return,
+    }
+    if let Some(material) = materials.get_mut(handle) {
+        material.emissive = emissive;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    fn toasts_after_check(world: &mut World) -> Vec<String> {
+        world.run_system_once(check_length_milestones);
+        world
+            .resource_mut::<Events<Toast>>()
+            .drain()
+            .map(|Toast(text)| text)
+            .collect()
+    }
+
+    #[test]
+    fn milestones_crossed_while_growing() {
+        assert_eq!(crossed_milestones(9, 10).collect::<Vec<_>>(), [10]);
+        assert_eq!(crossed_milestones(10, 11).count(), 0);
+        assert_eq!(crossed_milestones(9, 50).collect::<Vec<_>>(), [10, 25, 50]);
+        assert_eq!(crossed_milestones(12, 8).count(), 0);
+    }
+
+    #[test]
+    fn regrowing_after_a_shrink_does_not_retrigger() {
+        let mut world = World::new();
+        world.init_resource::<LongestSnake>();
+        world.init_resource::<Fanfare>();
+        world.init_resource::<Events<Toast>>();
+
+        let segments: Vec<Entity> = (0..10).map(|_| world.spawn(SnakeSegment).id()).collect();
+        assert_eq!(toasts_after_check(&mut world), ["Length 10!"]);
+
+        for &segment in &segments[..3] {
+            world.despawn(segment);
+        }
+        assert!(toasts_after_check(&mut world).is_empty());
+        assert_eq!(world.resource::<LongestSnake>().0, 10);
+
+        for _ in 0..3 {
+            world.spawn(SnakeSegment);
+        }
+        assert!(toasts_after_check(&mut world).is_empty());
+    }
+}
@@ -0,0 +1,100 @@
+//! Pausing and resuming a run
+//!
+//! Pausing stops the virtual clock, so the fixed ticks, food timers and
+//! segment slides all hold still while the frame keeps rendering. No time
+//! piles up while paused, the run resumes without a burst of ticks.
+
+use bevy::prelude::*;
+
+use crate::{
+    actions::{ActionState, PlayerAction},
+    hud::UiAssets,
+    AppState,
+};
+
+/// Tag for the root node of the overlay
+#[derive(Component, Debug, Default)]
+struct PauseUi;
+
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::Paused), (pause_time, spawn_pause_ui))
+            .add_systems(OnExit(AppState::Paused), (unpause_time, despawn_pause_ui))
+            .add_systems(
+                Update,
+                toggle_pause
+                    .run_if(in_state(AppState::Playing).or_else(in_state(AppState::Paused)))
+                    // the pause key answers the quit prompt while it is open
+                    .run_if(crate::quit::quit_prompt_closed)
+                    .before(crate::quit::answer_quit_prompt),
+            );
+    }
+}
+
+fn toggle_pause(
+    actions: Res<ActionState>,
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if !actions.pressed(PlayerAction::Pause) {
+        return;
+    }
+
+    let target = match state.get() {
+        AppState::Playing => AppState::Paused,
+        _ => AppState::Playing,
+    };
+    info!(target: "rsnake3d::toggle_pause", ?target);
+    next_state.set(target);
+}
+
+fn pause_time(mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+}
+
+fn unpause_time(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
+}
+
+fn spawn_pause_ui(mut commands: Commands, ui_assets: Res<UiAssets>) {
+    let text_style = |font_size| TextStyle {
+        font: ui_assets.font.clone(),
+        font_size,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(12.0),
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.4).into(),
+                ..default()
+            },
+            PauseUi,
+            Name::new("Pause"),
+        ))
+        .with_children(|panel| {
+            panel.spawn(TextBundle::from_section("Paused", text_style(48.0)));
+            panel.spawn(TextBundle::from_section(
+                "Press Escape to resume",
+                text_style(24.0),
+            ));
+        });
+}
+
+fn despawn_pause_ui(mut commands: Commands, ui: Query<Entity, With<PauseUi>>) {
+    for ent in ui.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+}
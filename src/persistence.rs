@@ -0,0 +1,388 @@
+//! Every file the game writes goes through here
+//!
+//! Writes land in a temporary sibling first and are renamed over the target,
+//! so a crash mid-write never leaves a truncated file behind. Saves queued
+//! with [`PendingSaves::save`] are debounced per path and flushed on exit.
+//! A failing path is reported once, as a toast and a warning, until it
+//! succeeds again. Temporary files a crash left behind are swept on startup.
+//!
+//! The best score is kept in `~/.rsnake3d/highscore.json` as
+//! `{ "high_score": 42 }`. Fields the game does not know are ignored, so
+//! newer versions can add to the file without breaking older ones.
+
+use std::{
+    collections::{HashMap, HashSet},
+    env, fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bevy::{app::AppExit, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{hud::Toast, AppState, Score};
+
+/// Directory in the user's home holding the files kept between sessions
+const DATA_DIR: &str = ".rsnake3d";
+
+/// File the high score is stored in, inside [`DATA_DIR`]
+const HIGH_SCORE_FILE: &str = "highscore.json";
+
+/// Shortest time between two writes of the high score file
+const HIGH_SCORE_SAVE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Best score ever reached, loaded on startup
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub struct HighScore(pub u32);
+
+/// Contents of the high score file
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct HighScoreFile {
+    high_score: u32,
+}
+
+/// Temporary sibling a write goes to before being renamed over `path`
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// Replaces the contents of `path` without ever leaving it half written
+///
+/// A temporary file left over by an earlier failed write is removed first.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let temp = temp_path(path);
+    if temp.exists() {
+        fs::remove_file(&temp)?;
+    }
+
+    fs::write(&temp, contents)?;
+    if let Err(err) = fs::rename(&temp, path) {
+        let _ = fs::remove_file(&temp);
+        return Err(err);
+    }
+    Ok(())
+}
+
+/// Removes the temporary siblings a crash mid-write left next to `paths`,
+/// returning how many were found
+pub fn sweep_temp_files(paths: impl IntoIterator<Item = PathBuf>) -> usize {
+    let mut swept = 0;
+    for temp in paths.into_iter().map(|path| temp_path(&path)) {
+        match fs::remove_file(&temp) {
+            Ok(()) => {
+                swept += 1;
+                debug!(target: "rsnake3d::persistence", "Removed stale {}", temp.display());
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => {
+                warn!(target: "rsnake3d::persistence", "Could not remove {}: {err}", temp.display());
+            }
+        }
+    }
+    swept
+}
+
+/// Where the high score is stored, `None` when there is no home directory
+pub fn high_score_path() -> Option<PathBuf> {
+    let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))?;
+    Some(PathBuf::from(home).join(DATA_DIR).join(HIGH_SCORE_FILE))
+}
+
+/// Reads the high score at `path`, 0 if the file is missing or malformed
+pub fn load_high_score(path: &Path) -> u32 {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return 0;
+    };
+
+    match serde_json::from_str::<HighScoreFile>(&contents) {
+        Ok(file) => file.high_score,
+        Err(err) => {
+            warn!(target: "rsnake3d::persistence", "Ignoring malformed {}: {err}", path.display());
+            0
+        }
+    }
+}
+
+/// Contents of a high score file holding `high_score`
+fn encode_high_score(high_score: u32) -> Vec<u8> {
+    let mut contents = serde_json::to_vec_pretty(&HighScoreFile { high_score })
+        .expect("a struct of integers always serializes");
+    contents.push(b'\n');
+    contents
+}
+
+/// Replaces `path` with [`write_atomic`], creating its directory if needed
+fn save_file(path: &Path, contents: &[u8]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    write_atomic(path, contents)
+}
+
+/// Rate limits writes to a single path, driven by an outside clock so it
+/// does not depend on Bevy's time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Debouncer {
+    min_interval: Duration,
+    last_write: Option<Duration>,
+}
+
+impl Debouncer {
+    pub fn new(min_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            last_write: None,
+        }
+    }
+
+    /// Whether a write may happen at `now`, recording it if so
+    pub fn try_write(&mut self, now: Duration) -> bool {
+        let ready = match self.last_write {
+            Some(last) => now.saturating_sub(last) >= self.min_interval,
+            None => true,
+        };
+        if ready {
+            self.last_write = Some(now);
+        }
+        ready
+    }
+}
+
+/// A queued write waiting for its debouncer
+#[derive(Debug)]
+struct PendingSave {
+    debouncer: Debouncer,
+    contents: Option<Vec<u8>>,
+}
+
+/// Debounced writes, only the latest contents queued for a path are written
+#[derive(Resource, Debug, Default)]
+pub struct PendingSaves {
+    saves: HashMap<PathBuf, PendingSave>,
+    /// Paths whose last write failed, so the error is not reported again
+    failing: HashSet<PathBuf>,
+}
+
+impl PendingSaves {
+    /// Queues `contents` for `path`, written at most once every `min_interval`
+    pub fn save(&mut self, path: impl Into<PathBuf>, contents: Vec<u8>, min_interval: Duration) {
+        let save = self
+            .saves
+            .entry(path.into())
+            .or_insert_with(|| PendingSave {
+                debouncer: Debouncer::new(min_interval),
+                contents: None,
+            });
+        save.contents = Some(contents);
+    }
+
+    /// Writes every save whose debouncer allows it, or all of them if `force`
+    fn flush(&mut self, now: Duration, force: bool, toasts: &mut EventWriter<Toast>) {
+        for (path, save) in &mut self.saves {
+            if save.contents.is_none() || !(save.debouncer.try_write(now) || force) {
+                continue;
+            }
+            let Some(contents) = save.contents.take() else {
+                continue;
+            };
+
+            match save_file(path, &contents) {
+                Ok(()) => {
+                    self.failing.remove(path);
+                    debug!(target: "rsnake3d::persistence", "Saved {}", path.display());
+                }
+                Err(err) => {
+                    if self.failing.insert(path.clone()) {
+                        warn!(target: "rsnake3d::persistence", "Could not save {}: {err}", path.display());
+                        toasts.send(Toast(format!("Could not save {}", path.display())));
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub struct PersistencePlugin;
+
+impl Plugin for PersistencePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<HighScore>()
+            .init_resource::<PendingSaves>()
+            .init_resource::<HighScore>()
+            .add_systems(Startup, (sweep_stale_temp_files, read_high_score))
+            .add_systems(OnEnter(AppState::GameOver), record_high_score)
+            .add_systems(Last, flush_pending_saves);
+    }
+}
+
+pub fn flush_pending_saves(
+    time: Res<Time<Real>>,
+    mut exit: EventReader<AppExit>,
+    mut saves: ResMut<PendingSaves>,
+    mut toasts: EventWriter<Toast>,
+) {
+    let exiting = exit.read().next().is_some();
+    saves.flush(time.elapsed(), exiting, &mut toasts);
+}
+
+fn sweep_stale_temp_files() {
+    let paths = high_score_path()
+        .into_iter()
+        .chain([PathBuf::from(crate::death_log::LOG_FILE)]);
+    let swept = sweep_temp_files(paths);
+    if swept > 0 {
+        info!(target: "rsnake3d::persistence", swept, "Removed files left by an interrupted save");
+    }
+}
+
+fn read_high_score(mut high_score: ResMut<HighScore>) {
+    if let Some(path) = high_score_path() {
+        high_score.0 = load_high_score(&path);
+        debug!(target: "rsnake3d::persistence", high_score = high_score.0);
+    }
+}
+
+/// Keeps the final score of a run if it beats the stored one
+fn record_high_score(
+    score: Res<Score>,
+    mut high_score: ResMut<HighScore>,
+    mut saves: ResMut<PendingSaves>,
+) {
+    if score.0 <= high_score.0 {
+        return;
+    }
+    high_score.0 = score.0;
+    info!(target: "rsnake3d::record_high_score", high_score = score.0, "New high score");
+
+    if let Some(path) = high_score_path() {
+        saves.save(path, encode_high_score(score.0), HIGH_SCORE_SAVE_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Empty directory unique to a test, under the system temp directory
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("rsnake3d-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn debouncer_lets_the_first_write_through() {
+        let mut debouncer = Debouncer::new(Duration::from_secs(5));
+        assert!(debouncer.try_write(Duration::from_secs(100)));
+    }
+
+    #[test]
+    fn debouncer_holds_writes_until_the_interval_passed() {
+        let mut debouncer = Debouncer::new(Duration::from_secs(5));
+        assert!(debouncer.try_write(Duration::ZERO));
+        assert!(!debouncer.try_write(Duration::from_secs(1)));
+        assert!(!debouncer.try_write(Duration::from_millis(4999)));
+        assert!(debouncer.try_write(Duration::from_secs(5)));
+        // refused writes do not push the next one back
+        assert!(!debouncer.try_write(Duration::from_secs(7)));
+        assert!(debouncer.try_write(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn debouncer_survives_a_clock_going_backwards() {
+        let mut debouncer = Debouncer::new(Duration::from_secs(5));
+        assert!(debouncer.try_write(Duration::from_secs(10)));
+        assert!(!debouncer.try_write(Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn writes_replace_the_target() {
+        let dir = test_dir("write");
+        let path = dir.join("file.txt");
+
+        write_atomic(&path, b"first").unwrap();
+        write_atomic(&path, b"second").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"second");
+        assert!(!temp_path(&path).exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn failed_renames_clean_up_the_temp_file() {
+        let dir = test_dir("rename");
+        // renaming a file over a non empty directory fails on every platform
+        let path = dir.join("taken");
+        fs::create_dir(&path).unwrap();
+        fs::write(path.join("inside"), b"").unwrap();
+        // and a stale temp file from an earlier crash is replaced
+        fs::write(temp_path(&path), b"stale").unwrap();
+
+        assert!(write_atomic(&path, b"contents").is_err());
+        assert!(!temp_path(&path).exists());
+        assert!(path.join("inside").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn stale_temp_files_are_swept() {
+        let dir = test_dir("sweep");
+        let stale = dir.join("stale.json");
+        let clean = dir.join("clean.json");
+        fs::write(&stale, b"kept").unwrap();
+        fs::write(temp_path(&stale), b"half writ").unwrap();
+
+        assert_eq!(sweep_temp_files([stale.clone(), clean]), 1);
+        assert!(!temp_path(&stale).exists());
+        assert_eq!(fs::read(&stale).unwrap(), b"kept");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn high_score_round_trips() {
+        let dir = test_dir("high-score");
+        let path = dir.join("nested").join(HIGH_SCORE_FILE);
+
+        save_file(&path, &encode_high_score(42)).unwrap();
+        assert_eq!(load_high_score(&path), 42);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn high_score_reads_any_valid_json() {
+        let dir = test_dir("high-score-json");
+        let path = dir.join(HIGH_SCORE_FILE);
+
+        fs::write(&path, r#"{"version": 2, "high_score": 17, "name": "snek"}"#).unwrap();
+        assert_eq!(load_high_score(&path), 17);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn malformed_high_scores_fall_back_to_zero() {
+        let dir = test_dir("high-score-malformed");
+        let path = dir.join(HIGH_SCORE_FILE);
+        assert_eq!(load_high_score(&path), 0);
+
+        for contents in [
+            "",
+            "42",
+            "{ \"high_score\": -1 }",
+            "{ \"score\": 3 }",
+            "{ high_score: 3 }",
+        ] {
+            fs::write(&path, contents).unwrap();
+            assert_eq!(load_high_score(&path), 0, "{contents:?} was accepted");
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,211 @@
+//! Cursor raycasts into the arena grid
+
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    grid_math,
+    hud::DebugOverlay,
+    locks::{KeyFor, Locked},
+    walls::Wall,
+    Food, GridBounds, GridConfig, MainCamera, Position, SnakeSegment,
+};
+
+/// The arena cell under the cursor, the first one the cursor ray enters
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HoveredCell(pub Option<IVec3>);
+
+pub struct PickingPlugin;
+
+impl Plugin for PickingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HoveredCell>()
+            .add_systems(Update, (update_hovered_cell, teleport_food_cheat).chain());
+    }
+}
+
+pub fn update_hovered_cell(
+    primary_window_q: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid: Res<GridConfig>,
+    bounds: Res<GridBounds>,
+    mut hovered: ResMut<HoveredCell>,
+) {
+    let cell = primary_window_q
+        .get_single()
+        .ok()
+        .and_then(Window::cursor_position)
+        .zip(camera.get_single().ok())
+        .and_then(|(cursor, (camera, camera_transform))| {
+            camera.viewport_to_world(camera_transform, cursor)
+        })
+        .and_then(|ray| ray_to_cell(ray, &grid, bounds.half_extent));
+
+    if hovered.0 != cell {
+        hovered.0 = cell;
+    }
+}
+
+/// First arena cell hit by `ray`, works for perspective and orthographic
+/// rays alike since only the ray itself is used
+pub fn ray_to_cell(ray: Ray, grid: &GridConfig, half_extent: IVec3) -> Option<IVec3> {
+    let half_size = (half_extent.as_vec3() + Vec3::splat(0.5)) * grid.cell_size;
+    let distance = ray_aabb_distance(ray, grid.origin - half_size, grid.origin + half_size)?;
+
+    // step a hair inside so points on a face land in the outer cell
+    let inside = ray.get_point(distance + grid.cell_size * 1e-3);
+    let cell = grid.world_to_cell(inside);
+
+    Some(grid_math::clamp_to_bounds(cell, half_extent))
+}
+
+/// Distance along `ray` to the box spanning `min..max`, slab method
+pub fn ray_aabb_distance(ray: Ray, min: Vec3, max: Vec3) -> Option<f32> {
+    let inv_dir = ray.direction.recip();
+    let t1 = (min - ray.origin) * inv_dir;
+    let t2 = (max - ray.origin) * inv_dir;
+
+    let t_near = t1.min(t2).max_element();
+    let t_far = t1.max(t2).min_element();
+
+    if t_near > t_far || t_far < 0.0 {
+        return None;
+    }
+
+    Some(t_near.max(0.0))
+}
+
+/// Shift-click moves a food to the hovered cell while the debug overlay is
+/// on, cells already taken are refused so the board stays valid
+#[allow(clippy::type_complexity)]
+fn teleport_food_cheat(
+    overlay: Res<DebugOverlay>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<MouseButton>>,
+    hovered: Res<HoveredCell>,
+    blocked: Query<&Position, (Or<(With<SnakeSegment>, With<Wall>)>, Without<Food>)>,
+    mut food: Query<(&mut Position, Option<&Locked>, Option<&KeyFor>), With<Food>>,
+) {
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if !overlay.0 || !shift || !buttons.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    let Some(cell) = hovered.0 else {
+        return;
+    };
+
+    let taken = blocked
+        .iter()
+        .chain(food.iter().map(|(pos, ..)| pos))
+        .any(|p| p.0 == cell);
+    if taken {
+        debug!(target: "rsnake3d::teleport_food_cheat", ?cell, "Cell taken");
+        return;
+    }
+
+    // pairs stay where they spawned, only plain food moves
+    let Some((mut pos, ..)) = food
+        .iter_mut()
+        .find(|(_, locked, key)| locked.is_none() && key.is_none())
+    else {
+        return;
+    };
+    pos.0 = cell;
+
+    debug!(target: "rsnake3d::teleport_food_cheat", ?cell);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::render::camera::CameraProjection;
+
+    use super::*;
+
+    const HALF_EXTENT: IVec3 = IVec3::splat(3);
+
+    /// Ray through `ndc` of a camera at `eye` looking at the origin, the same
+    /// unprojection `Camera::viewport_to_world` ends with
+    fn camera_ray(projection: &impl CameraProjection, eye: Vec3, up: Vec3, ndc: Vec2) -> Ray {
+        let camera = Transform::from_translation(eye).looking_at(Vec3::ZERO, up);
+        let ndc_to_world = camera.compute_matrix() * projection.get_projection_matrix().inverse();
+        let near = ndc_to_world.project_point3(ndc.extend(1.0));
+        let far = ndc_to_world.project_point3(ndc.extend(f32::EPSILON));
+        Ray {
+            origin: near,
+            direction: (far - near).normalize(),
+        }
+    }
+
+    #[test]
+    fn rays_hit_the_closest_face_of_a_box() {
+        let ray = Ray {
+            origin: Vec3::new(-5.0, 0.5, 0.5),
+            direction: Vec3::X,
+        };
+        assert_eq!(ray_aabb_distance(ray, Vec3::ZERO, Vec3::ONE), Some(5.0));
+
+        let inside = Ray {
+            origin: Vec3::splat(0.5),
+            direction: Vec3::NEG_Y,
+        };
+        assert_eq!(ray_aabb_distance(inside, Vec3::ZERO, Vec3::ONE), Some(0.0));
+
+        let away = Ray {
+            direction: Vec3::NEG_X,
+            ..ray
+        };
+        assert_eq!(ray_aabb_distance(away, Vec3::ZERO, Vec3::ONE), None);
+
+        let beside = Ray {
+            origin: Vec3::new(-5.0, 2.5, 0.5),
+            direction: Vec3::X,
+        };
+        assert_eq!(ray_aabb_distance(beside, Vec3::ZERO, Vec3::ONE), None);
+    }
+
+    #[test]
+    fn perspective_rays_pick_the_first_cell_entered() {
+        let projection = PerspectiveProjection::default();
+        let grid = GridConfig::default();
+
+        let above = camera_ray(&projection, Vec3::Y * 10.0, Vec3::Z, Vec2::ZERO);
+        assert_eq!(
+            ray_to_cell(above, &grid, HALF_EXTENT),
+            Some(IVec3::new(0, 3, 0))
+        );
+
+        let diagonal = camera_ray(&projection, Vec3::splat(10.0), Vec3::Y, Vec2::ZERO);
+        assert_eq!(ray_to_cell(diagonal, &grid, HALF_EXTENT), Some(HALF_EXTENT));
+
+        let corner = camera_ray(&projection, Vec3::Y * 30.0, Vec3::Z, Vec2::ONE);
+        assert_eq!(ray_to_cell(corner, &grid, HALF_EXTENT), None);
+    }
+
+    #[test]
+    fn orthographic_rays_pick_the_cell_under_the_cursor() {
+        let projection = OrthographicProjection {
+            area: Rect::new(-5.0, -5.0, 5.0, 5.0),
+            ..default()
+        };
+        let grid = GridConfig {
+            origin: Vec3::new(0.0, 0.0, -1.0),
+            ..default()
+        };
+        let eye = Vec3::Z * 20.0;
+
+        let center = camera_ray(&projection, eye, Vec3::Y, Vec2::ZERO);
+        assert_eq!(
+            ray_to_cell(center, &grid, HALF_EXTENT),
+            Some(IVec3::new(0, 0, 3))
+        );
+
+        let right = camera_ray(&projection, eye, Vec3::Y, Vec2::new(0.4, -0.2));
+        assert_eq!(
+            ray_to_cell(right, &grid, HALF_EXTENT),
+            Some(IVec3::new(2, -1, 3))
+        );
+
+        let outside = camera_ray(&projection, eye, Vec3::Y, Vec2::new(0.9, 0.0));
+        assert_eq!(ray_to_cell(outside, &grid, HALF_EXTENT), None);
+    }
+}
@@ -0,0 +1,305 @@
+//! Asks before closing the window in the middle of a run
+//!
+//! Window close requests are handled here instead of by Bevy's
+//! `WindowPlugin`, so pending saves are flushed before the app exits.
+//! A second close request shortly after the first quits right away, so
+//! the confirmation can never keep the player from closing the game. The
+//! virtual clock stops while the confirmation is up, so the run cannot end
+//! behind it.
+
+use bevy::{app::AppExit, prelude::*, window::WindowCloseRequested};
+
+use crate::{
+    actions::{ActionState, PlayerAction},
+    hud::UiAssets,
+    AppState, TickTimer,
+};
+
+/// Seconds within which a second close request quits without asking
+const FORCE_QUIT_SECONDS: f32 = 3.0;
+
+/// Background of a button of the dialog
+const BUTTON_COLOR: Color = Color::rgba(0.15, 0.15, 0.15, 0.8);
+
+/// Background of the button under the cursor
+const HOVERED_BUTTON_COLOR: Color = Color::rgba(0.25, 0.55, 0.3, 0.9);
+
+/// Tag for the root node of the quit confirmation
+#[derive(Component, Debug, Default)]
+struct QuitDialog;
+
+/// What a button of the dialog answers
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum QuitButton {
+    Quit,
+    Cancel,
+}
+
+/// Set while the confirmation is on screen, counts down the force quit window
+#[derive(Resource, Debug, Default)]
+pub struct QuitPrompt(Option<Timer>);
+
+/// Run condition, whether the quit confirmation is off screen
+pub fn quit_prompt_closed(prompt: Res<QuitPrompt>) -> bool {
+    prompt.0.is_none()
+}
+
+pub struct QuitPlugin;
+
+impl Plugin for QuitPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<QuitPrompt>()
+            .add_systems(PostStartup, setup_quit_dialog)
+            .add_systems(
+                Update,
+                (
+                    handle_close_requests,
+                    answer_quit_prompt,
+                    pause_behind_prompt,
+                    show_quit_dialog,
+                    highlight_quit_buttons,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn setup_quit_dialog(mut commands: Commands, ui_assets: Res<UiAssets>) {
+    let text_style = |font_size| TextStyle {
+        font: ui_assets.font.clone(),
+        font_size,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Percent(40.0),
+                    left: Val::Percent(30.0),
+                    padding: UiRect::all(Val::Px(12.0)),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(12.0),
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.8).into(),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            QuitDialog,
+            Name::new("Quit Dialog"),
+        ))
+        .with_children(|dialog| {
+            dialog.spawn(TextBundle::from_section(
+                "Quit? Your run will be lost\nY to quit, N or Esc to keep playing",
+                text_style(24.0),
+            ));
+            dialog
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(16.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|row| {
+                    for (button, label) in
+                        [(QuitButton::Quit, "Quit"), (QuitButton::Cancel, "Cancel")]
+                    {
+                        row.spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    width: Val::Px(140.0),
+                                    padding: UiRect::all(Val::Px(8.0)),
+                                    justify_content: JustifyContent::Center,
+                                    ..default()
+                                },
+                                background_color: BUTTON_COLOR.into(),
+                                ..default()
+                            },
+                            button,
+                        ))
+                        .with_children(|button_node| {
+                            button_node.spawn(TextBundle::from_section(label, text_style(24.0)));
+                        });
+                    }
+                });
+        });
+}
+
+fn handle_close_requests(
+    time: Res<Time<Real>>,
+    tick: Res<TickTimer>,
+    state: Res<State<AppState>>,
+    mut requests: EventReader<WindowCloseRequested>,
+    mut prompt: ResMut<QuitPrompt>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if let Some(timer) = &mut prompt.0 {
+        timer.tick(time.delta());
+    }
+
+    if requests.read().count() == 0 {
+        return;
+    }
+
+    let forced = prompt.0.as_ref().is_some_and(|timer| !timer.finished());
+    // nothing is lost before the first tick or once the run is over
+    if forced || tick.count == 0 || *state.get() == AppState::GameOver {
+        info!(target: "rsnake3d::handle_close_requests", forced, "Quitting");
+        exit.send(AppExit);
+        return;
+    }
+
+    prompt.0 = Some(Timer::from_seconds(FORCE_QUIT_SECONDS, TimerMode::Once));
+}
+
+/// Y or the Quit button quits, N, the pause key or the Cancel button keep
+/// playing
+pub fn answer_quit_prompt(
+    actions: Res<ActionState>,
+    buttons: Query<(&Interaction, &QuitButton), Changed<Interaction>>,
+    mut prompt: ResMut<QuitPrompt>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if prompt.0.is_none() {
+        return;
+    }
+
+    let clicked = |target| {
+        buttons
+            .iter()
+            .any(|(interaction, button)| *interaction == Interaction::Pressed && *button == target)
+    };
+
+    if actions.pressed(PlayerAction::Confirm) || clicked(QuitButton::Quit) {
+        info!(target: "rsnake3d::answer_quit_prompt", "Quitting");
+        exit.send(AppExit);
+    } else if actions.pressed(PlayerAction::Cancel)
+        || actions.pressed(PlayerAction::Pause)
+        || clicked(QuitButton::Cancel)
+    {
+        prompt.0 = None;
+    }
+}
+
+/// Stops the virtual clock while the prompt is open, a run paused before
+/// it opened stays paused once it closes
+fn pause_behind_prompt(
+    prompt: Res<QuitPrompt>,
+    state: Res<State<AppState>>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    if !prompt.is_changed() {
+        return;
+    }
+
+    if prompt.0.is_some() {
+        time.pause();
+    } else if *state.get() != AppState::Paused {
+        time.unpause();
+    }
+}
+
+fn show_quit_dialog(prompt: Res<QuitPrompt>, mut dialog: Query<&mut Visibility, With<QuitDialog>>) {
+    for mut visibility in dialog.iter_mut() {
+        let target = if prompt.0.is_some() {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        visibility.set_if_neq(target);
+    }
+}
+
+fn highlight_quit_buttons(
+    mut buttons: Query<
+        (&Interaction, &mut BackgroundColor),
+        (With<QuitButton>, Changed<Interaction>),
+    >,
+) {
+    for (interaction, mut background) in buttons.iter_mut() {
+        let color = match interaction {
+            Interaction::Hovered | Interaction::Pressed => HOVERED_BUTTON_COLOR,
+            Interaction::None => BUTTON_COLOR,
+        };
+        background.set_if_neq(color.into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    fn prompt_world(action: PlayerAction) -> World {
+        let mut world = World::new();
+        let mut actions = ActionState::default();
+        actions.push(action);
+        world.insert_resource(actions);
+        world.insert_resource(QuitPrompt(Some(Timer::from_seconds(
+            FORCE_QUIT_SECONDS,
+            TimerMode::Once,
+        ))));
+        world.init_resource::<Events<AppExit>>();
+        world
+    }
+
+    #[test]
+    fn pause_key_cancels_the_prompt() {
+        let mut world = prompt_world(PlayerAction::Pause);
+        world.run_system_once(answer_quit_prompt);
+        assert!(world.resource::<QuitPrompt>().0.is_none());
+        assert!(world.resource::<Events<AppExit>>().is_empty());
+    }
+
+    #[test]
+    fn confirm_quits() {
+        let mut world = prompt_world(PlayerAction::Confirm);
+        world.run_system_once(answer_quit_prompt);
+        assert!(!world.resource::<Events<AppExit>>().is_empty());
+    }
+
+    #[test]
+    fn cancel_button_closes_the_prompt() {
+        let mut world = prompt_world(PlayerAction::Zoom(1.0));
+        world.spawn((Interaction::Pressed, QuitButton::Cancel));
+        world.run_system_once(answer_quit_prompt);
+        assert!(world.resource::<QuitPrompt>().0.is_none());
+        assert!(world.resource::<Events<AppExit>>().is_empty());
+    }
+
+    #[test]
+    fn quit_button_quits() {
+        let mut world = prompt_world(PlayerAction::Zoom(1.0));
+        world.spawn((Interaction::Pressed, QuitButton::Quit));
+        world.run_system_once(answer_quit_prompt);
+        assert!(!world.resource::<Events<AppExit>>().is_empty());
+    }
+
+    #[test]
+    fn the_run_holds_still_behind_the_prompt() {
+        for (state, paused_after) in [(AppState::Playing, false), (AppState::Paused, true)] {
+            let mut world = prompt_world(PlayerAction::Zoom(1.0));
+            world.insert_resource(State::new(state));
+            world.init_resource::<Time<Virtual>>();
+            if state == AppState::Paused {
+                world.resource_mut::<Time<Virtual>>().pause();
+            }
+
+            world.run_system_once(pause_behind_prompt);
+            assert!(world.resource::<Time<Virtual>>().is_paused());
+
+            world.resource_mut::<QuitPrompt>().0 = None;
+            world.run_system_once(pause_behind_prompt);
+            assert_eq!(
+                world.resource::<Time<Virtual>>().is_paused(),
+                paused_after,
+                "closing the prompt in {state:?}"
+            );
+        }
+    }
+}
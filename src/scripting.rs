@@ -0,0 +1,331 @@
+//! Rhai hooks letting levels tweak the rules without recompiling
+//!
+//! A script may define any of `on_start()`, `on_tick()` and `on_eat()`.
+//! The game state is read with `head_x()`, `head_y()`, `head_z()`,
+//! `length()`, `score()` and `tick()`, and changed with
+//! `spawn_food(x, y, z)`, `set_tick_interval(ms)` and `toast(message)`.
+//! `on_start()` runs again at the start of every run.
+
+use std::{
+    cell::RefCell,
+    fs,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use rhai::{Engine, Scope, AST};
+
+use crate::{
+    grid_math, hud::Toast, walls::Wall, EatEvent, Food, FoodBundle, GridBounds, GridConfig,
+    Position, Score, SnakeAssets, SnakeHead, SnakeSegment, StartRun, TickTimer,
+};
+
+/// Operations a single callback may run before it is aborted
+const MAX_OPERATIONS: u64 = 50_000;
+
+/// Allowed values for `set_tick_interval`, in milliseconds
+const TICK_INTERVAL_MS: RangeInclusive<i64> = 100..=5000;
+
+/// Requests queued by a script, applied once the callback returns
+#[derive(Debug, PartialEq, Eq)]
+enum ScriptCommand {
+    SpawnFood(IVec3),
+    SetTickInterval(Duration),
+    Toast(String),
+}
+
+/// Game state readable from scripts, refreshed before each callback
+#[derive(Debug, Default, Clone, Copy)]
+struct ScriptState {
+    head: IVec3,
+    length: i64,
+    score: i64,
+    tick: i64,
+}
+
+/// The loaded script, kept on the main thread as the engine is not `Send`
+struct ScriptHost {
+    engine: Engine,
+    ast: AST,
+    state: Rc<RefCell<ScriptState>>,
+    queue: Rc<RefCell<Vec<ScriptCommand>>>,
+    /// Cleared after the first error so a broken script stops running
+    enabled: bool,
+    /// Whether `on_start` ran for the current run
+    started: bool,
+}
+
+impl ScriptHost {
+    fn load(path: &Path) -> Result<Self, String> {
+        let source = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        Self::from_source(&source)
+    }
+
+    fn from_source(source: &str) -> Result<Self, String> {
+        let state = Rc::new(RefCell::new(ScriptState::default()));
+        let queue = Rc::new(RefCell::new(Vec::new()));
+
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.set_max_call_levels(16);
+        engine.set_max_string_size(1024);
+        engine.set_max_array_size(1024);
+        engine.set_max_map_size(256);
+
+        let getters: [(&str, fn(&ScriptState) -> i64); 6] = [
+            ("head_x", |s| i64::from(s.head.x)),
+            ("head_y", |s| i64::from(s.head.y)),
+            ("head_z", |s| i64::from(s.head.z)),
+            ("length", |s| s.length),
+            ("score", |s| s.score),
+            ("tick", |s| s.tick),
+        ];
+        for (name, getter) in getters {
+            let state = state.clone();
+            engine.register_fn(name, move || getter(&state.borrow()));
+        }
+
+        let spawn_queue = queue.clone();
+        engine.register_fn("spawn_food", move |x: i64, y: i64, z: i64| {
+            // out of range values would otherwise wrap onto a valid cell
+            let (Ok(x), Ok(y), Ok(z)) = (i32::try_from(x), i32::try_from(y), i32::try_from(z))
+            else {
+                warn!(target: "rsnake3d::scripting", "spawn_food out of range ({x}, {y}, {z})");
+                return;
+            };
+            spawn_queue
+                .borrow_mut()
+                .push(ScriptCommand::SpawnFood(IVec3::new(x, y, z)));
+        });
+
+        let interval_queue = queue.clone();
+        engine.register_fn("set_tick_interval", move |millis: i64| {
+            let millis = millis.clamp(*TICK_INTERVAL_MS.start(), *TICK_INTERVAL_MS.end());
+            interval_queue
+                .borrow_mut()
+                .push(ScriptCommand::SetTickInterval(Duration::from_millis(
+                    millis as u64,
+                )));
+        });
+
+        let toast_queue = queue.clone();
+        engine.register_fn("toast", move |message: &str| {
+            toast_queue
+                .borrow_mut()
+                .push(ScriptCommand::Toast(message.to_string()));
+        });
+
+        let ast = engine.compile(source).map_err(|err| err.to_string())?;
+
+        Ok(Self {
+            engine,
+            ast,
+            state,
+            queue,
+            enabled: true,
+            started: false,
+        })
+    }
+
+    fn has_callback(&self, name: &str) -> bool {
+        self.ast.iter_functions().any(|f| f.name == name)
+    }
+
+    /// Runs a callback if the script defines it, disabling the script on
+    /// error with a toast in place of whatever the callback queued
+    fn call(&mut self, name: &str) {
+        if !self.enabled || !self.has_callback(name) {
+            return;
+        }
+
+        let mut scope = Scope::new();
+        if let Err(err) = self.engine.call_fn::<()>(&mut scope, &self.ast, name, ()) {
+            self.enabled = false;
+            error!(target: "rsnake3d::scripting", "{name} failed, script disabled: {err}");
+            *self.queue.borrow_mut() = vec![ScriptCommand::Toast(format!(
+                "Script disabled, {name} failed"
+            ))];
+        }
+    }
+}
+
+pub struct ScriptingPlugin;
+
+impl Plugin for ScriptingPlugin {
+    fn build(&self, app: &mut App) {
+        let Some(path) = script_path(std::env::args().skip(1)) else {
+            return;
+        };
+
+        match ScriptHost::load(&path) {
+            Ok(host) => {
+                info!(target: "rsnake3d::scripting", "Loaded script {}", path.display());
+                app.insert_non_send_resource(host)
+                    .add_systems(StartRun, restart_script)
+                    .add_systems(
+                        FixedUpdate,
+                        run_script_hooks.in_set(crate::TickSet::PostTick),
+                    );
+            }
+            Err(err) => {
+                error!(target: "rsnake3d::scripting", "Could not load {}: {err}", path.display())
+            }
+        }
+    }
+}
+
+/// Reads `--script <path>` from the process arguments
+fn script_path(args: impl IntoIterator<Item = String>) -> Option<PathBuf> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if let Some(path) = arg.strip_prefix("--script=") {
+            return Some(PathBuf::from(path));
+        }
+        if arg == "--script" {
+            return args.next().map(PathBuf::from);
+        }
+    }
+    None
+}
+
+/// Lets `on_start` run again for the new run
+fn restart_script(mut host: NonSendMut<ScriptHost>) {
+    host.started = false;
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_script_hooks(
+    mut commands: Commands,
+    mut host: NonSendMut<ScriptHost>,
+    mut tick: ResMut<TickTimer>,
+    mut eat_reader: EventReader<EatEvent>,
+    mut toasts: EventWriter<Toast>,
+    head: Query<&Position, With<SnakeHead>>,
+    segments: Query<(), With<SnakeSegment>>,
+    occupied: Query<&Position, Or<(With<SnakeSegment>, With<Food>, With<Wall>)>>,
+    score: Res<Score>,
+    grid: Res<GridConfig>,
+    bounds: Res<GridBounds>,
+    snake_assets: Res<SnakeAssets>,
+) {
+    let eaten = eat_reader.read().count();
+
+    *host.state.borrow_mut() = ScriptState {
+        head: head.get_single().map(|p| p.0).unwrap_or_default(),
+        length: segments.iter().count() as i64,
+        score: i64::from(score.0),
+        tick: tick.count as i64,
+    };
+
+    if host.enabled && !host.started {
+        host.started = true;
+        host.call("on_start");
+    }
+    host.call("on_tick");
+    for _ in 0..eaten {
+        host.call("on_eat");
+    }
+
+    let queued = std::mem::take(&mut *host.queue.borrow_mut());
+    for command in queued {
+        match command {
+            ScriptCommand::SpawnFood(pos) => {
+                let free = grid_math::is_in_bounds(pos, bounds.half_extent)
+                    && occupied.iter().all(|p| p.0 != pos);
+                if !free {
+                    warn!(target: "rsnake3d::scripting", "spawn_food on a taken cell {pos:?}");
+                    continue;
+                }
+
+                commands.spawn(FoodBundle {
+                    position: Position(pos),
+                    mesh: snake_assets.food_mesh.clone(),
+                    material: snake_assets.food_material.clone(),
+                    transform: grid.cell_transform(pos),
+                    ..default()
+                });
+            }
+            ScriptCommand::SetTickInterval(interval) => tick.set_override(interval),
+            ScriptCommand::Toast(message) => {
+                info!(target: "rsnake3d::scripting", "{message}");
+                toasts.send(Toast(message));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queued(host: &ScriptHost) -> Vec<ScriptCommand> {
+        std::mem::take(&mut *host.queue.borrow_mut())
+    }
+
+    #[test]
+    fn a_runaway_loop_disables_the_script() {
+        let mut host = ScriptHost::from_source("fn on_tick() { loop { } }").unwrap();
+
+        host.call("on_tick");
+        assert!(!host.enabled);
+        assert_eq!(
+            queued(&host),
+            [ScriptCommand::Toast(
+                "Script disabled, on_tick failed".into()
+            )]
+        );
+    }
+
+    #[test]
+    fn a_failing_callback_stops_later_ones() {
+        let script = r#"
+            fn on_start() { toast("starting"); throw "broken"; }
+            fn on_tick() { toast("tick"); }
+        "#;
+        let mut host = ScriptHost::from_source(script).unwrap();
+
+        host.call("on_start");
+        host.call("on_tick");
+        // what the failed callback queued is dropped too
+        assert_eq!(
+            queued(&host),
+            [ScriptCommand::Toast(
+                "Script disabled, on_start failed".into()
+            )]
+        );
+    }
+
+    #[test]
+    fn tick_intervals_are_clamped() {
+        let script = r#"
+            fn on_tick() {
+                set_tick_interval(1);
+                set_tick_interval(250);
+                set_tick_interval(1000000);
+            }
+        "#;
+        let mut host = ScriptHost::from_source(script).unwrap();
+
+        host.call("on_tick");
+        assert_eq!(
+            queued(&host),
+            [100, 250, 5000].map(|ms| ScriptCommand::SetTickInterval(Duration::from_millis(ms)))
+        );
+    }
+
+    #[test]
+    fn out_of_range_cells_are_not_spawned() {
+        let script = "fn on_tick() { spawn_food(4294967296, 0, 0); spawn_food(1, -2, 3); }";
+        let mut host = ScriptHost::from_source(script).unwrap();
+
+        host.call("on_tick");
+        assert!(host.enabled);
+        assert_eq!(
+            queued(&host),
+            [ScriptCommand::SpawnFood(IVec3::new(1, -2, 3))]
+        );
+    }
+}
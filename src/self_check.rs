@@ -0,0 +1,189 @@
+//! Report of which optional features run degraded, built once at startup
+//!
+//! Once the UI font has finished loading, every optional piece is listed as
+//! found, replaced by a fallback or missing, and the list is logged as a
+//! single block. `--self-check` exits right after, with a failure code if
+//! anything mandatory is missing, so a headless smoke test can gate on it.
+//! The failure code is only set once pending saves are flushed.
+//!
+//! Meshes are built in code and sounds have no asset files, so the UI font
+//! is the only asset checked.
+
+use std::fmt;
+
+use bevy::{app::AppExit, asset::LoadState, prelude::*};
+
+use crate::hud::UiAssets;
+
+/// How an item of the report was resolved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckStatus {
+    Found,
+    /// Replaced by what is described
+    Fallback(&'static str),
+    Missing,
+}
+
+/// A single entry of the report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckItem {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    /// Whether the game is unusable without it
+    pub mandatory: bool,
+}
+
+/// Every optional piece and how it was resolved, `None` until the check ran
+#[derive(Resource, Debug, Default, Clone)]
+pub struct SelfCheckReport(pub Option<Vec<CheckItem>>);
+
+impl SelfCheckReport {
+    /// Whether every mandatory item was found
+    pub fn passed(&self) -> bool {
+        self.0.as_ref().is_some_and(|items| {
+            items
+                .iter()
+                .all(|item| !item.mandatory || item.status == CheckStatus::Found)
+        })
+    }
+}
+
+impl fmt::Display for SelfCheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(items) = &self.0 else {
+            return write!(f, "self check pending");
+        };
+        for item in items {
+            let status = match &item.status {
+                CheckStatus::Found => "found".to_string(),
+                CheckStatus::Fallback(fallback) => format!("fallback, {fallback}"),
+                CheckStatus::Missing => "missing".to_string(),
+            };
+            let mandatory = if item.mandatory { " (mandatory)" } else { "" };
+            writeln!(f, "  {}{mandatory}: {status}", item.name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether the app quits once the report is ready, from the command line
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct ExitAfterCheck(bool);
+
+pub struct SelfCheckPlugin;
+
+impl Plugin for SelfCheckPlugin {
+    fn build(&self, app: &mut App) {
+        let exit = std::env::args().skip(1).any(|arg| arg == "--self-check");
+        app.init_resource::<SelfCheckReport>()
+            .insert_resource(ExitAfterCheck(exit))
+            .add_systems(Update, run_self_check)
+            .add_systems(
+                Last,
+                set_exit_code.after(crate::persistence::flush_pending_saves),
+            );
+    }
+}
+
+/// Status of an asset the game cannot do without, `None` while it is
+/// still loading
+fn mandatory_status(load_state: Option<LoadState>) -> Option<CheckStatus> {
+    match load_state {
+        Some(LoadState::Loaded) => Some(CheckStatus::Found),
+        Some(LoadState::Failed) | None => Some(CheckStatus::Missing),
+        Some(_) => None,
+    }
+}
+
+fn run_self_check(
+    asset_server: Res<AssetServer>,
+    ui_assets: Res<UiAssets>,
+    exit_after: Res<ExitAfterCheck>,
+    mut report: ResMut<SelfCheckReport>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if report.0.is_some() {
+        return;
+    }
+
+    let Some(font) = mandatory_status(asset_server.get_load_state(&ui_assets.font)) else {
+        return;
+    };
+
+    report.0 = Some(vec![
+        CheckItem {
+            name: "UI font",
+            status: font,
+            mandatory: true,
+        },
+        CheckItem {
+            name: "scripting",
+            status: if cfg!(feature = "scripting") {
+                CheckStatus::Found
+            } else {
+                CheckStatus::Fallback("not compiled in")
+            },
+            mandatory: false,
+        },
+    ]);
+
+    if report.passed() {
+        info!(target: "rsnake3d::run_self_check", "Self check:\n{}", *report);
+    } else {
+        error!(target: "rsnake3d::run_self_check", "Self check failed:\n{}", *report);
+    }
+
+    if exit_after.0 {
+        exit.send(AppExit);
+    }
+}
+
+/// Fails the process once the app is on its way out, after the `Last`
+/// systems that have to run before it, like flushing saves
+fn set_exit_code(
+    exit_after: Res<ExitAfterCheck>,
+    report: Res<SelfCheckReport>,
+    mut exit: EventReader<AppExit>,
+) {
+    if exit.read().next().is_none() || !exit_after.0 || report.0.is_none() {
+        return;
+    }
+    if !report.passed() {
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mandatory_assets_are_missing_once_they_fail() {
+        assert_eq!(
+            mandatory_status(Some(LoadState::Loaded)),
+            Some(CheckStatus::Found)
+        );
+        assert_eq!(
+            mandatory_status(Some(LoadState::Failed)),
+            Some(CheckStatus::Missing)
+        );
+        assert_eq!(mandatory_status(None), Some(CheckStatus::Missing));
+        assert_eq!(mandatory_status(Some(LoadState::Loading)), None);
+    }
+
+    #[test]
+    fn only_mandatory_items_fail_the_check() {
+        let item = |status, mandatory| CheckItem {
+            name: "item",
+            status,
+            mandatory,
+        };
+        assert!(!SelfCheckReport(None).passed());
+        assert!(SelfCheckReport(Some(vec![
+            item(CheckStatus::Found, true),
+            item(CheckStatus::Fallback("none"), false),
+        ]))
+        .passed());
+        assert!(!SelfCheckReport(Some(vec![item(CheckStatus::Missing, true)])).passed());
+    }
+}
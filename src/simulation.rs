@@ -0,0 +1,650 @@
+//! The game rules as a plain state machine, one `step` per gameplay tick
+//!
+//! Mirrors what the ECS systems do each tick: the head moves, the body
+//! follows, food grows the snake one segment per tick into the cell the tail
+//! vacated and respawns anywhere free in the arena, and leaving the arena or
+//! running into the body or a wall ends the run. With the ouroboros rule on
+//! biting the tail tip wins it. Food left uneaten relocates after a while,
+//! and some of it is golden or slow motion. Presentation only effects like
+//! the slow motion itself are left out as they do not change the board.
+//!
+//! The food kinds and their odds live here, the app uses them too, and the
+//! app tests cross-check its board hash against [`Simulation::state_hash`].
+
+use std::{
+    collections::{hash_map::DefaultHasher, VecDeque},
+    hash::{Hash, Hasher},
+};
+
+use bevy::prelude::{IVec3, Reflect, ReflectResource, Resource};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    grid_math,
+    turns::{TurnBuffer, TurnPolicy},
+    Direction,
+};
+
+/// Shortest snake, head included, allowed to close the loop
+pub const OUROBOROS_MIN_LENGTH: usize = 8;
+
+/// Cells of the head and the tail at the start of a run
+pub const START_CELLS: [IVec3; 2] = [IVec3::ZERO, IVec3::NEG_Y];
+
+/// Chance for a newly spawned food to be a slow motion pickup
+pub const SLOW_MOTION_CHANCE: f64 = 0.1;
+
+/// Chance for a newly spawned food that is not a slow motion pickup to be golden
+pub const GOLDEN_CHANCE: f64 = 0.15;
+
+/// Segments grown by eating a golden food
+pub const GOLDEN_GROWTH: u32 = 3;
+
+/// Points scored by eating a golden food
+pub const GOLDEN_POINTS: u32 = 5;
+
+/// Points scored by a locked food once its key has been eaten
+pub const UNLOCKED_POINTS: u32 = 3;
+
+/// The kinds of food the snake can eat
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum FoodKind {
+    Regular,
+    SlowMotion,
+    Key,
+    Unlocked,
+    Golden,
+}
+
+impl FoodKind {
+    /// Points scored by eating the food
+    pub fn points(self) -> u32 {
+        match self {
+            FoodKind::Unlocked => UNLOCKED_POINTS,
+            FoodKind::Golden => GOLDEN_POINTS,
+            _ => 1,
+        }
+    }
+
+    /// Segments the snake grows by eating the food
+    pub fn growth(self) -> u32 {
+        match self {
+            FoodKind::Golden => GOLDEN_GROWTH,
+            _ => 1,
+        }
+    }
+}
+
+/// A random free cell of the arena spanning `-half_extent..=half_extent`
+///
+/// Cells within `radius` (Chebyshev) of any `protected` cell are refused,
+/// `None` when nothing is left to pick from.
+pub fn pick_food_cell(
+    rng: &mut impl Rng,
+    half_extent: IVec3,
+    occupied: &[IVec3],
+    protected: &[IVec3],
+    radius: u32,
+) -> Option<IVec3> {
+    let free: Vec<IVec3> = (-half_extent.x..=half_extent.x)
+        .flat_map(|x| (-half_extent.y..=half_extent.y).map(move |y| (x, y)))
+        .flat_map(|(x, y)| (-half_extent.z..=half_extent.z).map(move |z| IVec3::new(x, y, z)))
+        .filter(|cell| !occupied.contains(cell))
+        .filter(|cell| {
+            protected
+                .iter()
+                .all(|p| grid_math::chebyshev_distance(*cell, *p) > radius)
+        })
+        .collect();
+
+    if free.is_empty() {
+        return None;
+    }
+    Some(free[rng.gen_range(0..free.len())])
+}
+
+/// Hash of a board, shared by [`Simulation::state_hash`] and the app's
+/// per-tick board hash so the two can be compared tick by tick
+///
+/// `body` leaves the head out. Body and food cells are hashed one by one
+/// and summed, so the cost is linear in the snake length and the order the
+/// cells come in does not matter.
+pub fn board_hash(
+    head: Option<(IVec3, Direction)>,
+    body: impl IntoIterator<Item = IVec3>,
+    food: impl IntoIterator<Item = IVec3>,
+    score: u32,
+    tick: u64,
+) -> u64 {
+    let cells = body
+        .into_iter()
+        .map(|cell| hash_one(("body", cell)))
+        .chain(food.into_iter().map(|cell| hash_one(("food", cell))))
+        .fold(0u64, u64::wrapping_add);
+
+    hash_one((head, cells, score, tick))
+}
+
+fn hash_one(value: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Rule toggles of a run
+///
+/// Built from [`Rules::default`] and the `with_` methods, so new rules can
+/// be added without breaking embedders. The defaults are the app's.
+///
+/// The app keeps it as a resource to pick the turn policy, the other rules
+/// still have their own resources there.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+#[non_exhaustive]
+pub struct Rules {
+    /// The arena spans `-half_extent..=half_extent` on each axis
+    pub half_extent: IVec3,
+    /// Biting exactly the tail tip wins once the snake is long enough
+    pub ouroboros: bool,
+    /// Chebyshev radius around the starting snake kept clear of the first food
+    pub spawn_protection: u32,
+    /// Leaving through a face enters through the opposite one instead of
+    /// ending the run
+    pub wrap: bool,
+    /// Ticks a food stays in place before relocating, 0 keeps it forever
+    pub food_lifetime: u32,
+    /// How turns requested between two ticks are resolved
+    pub turn_policy: TurnPolicy,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            half_extent: IVec3::splat(5),
+            ouroboros: false,
+            spawn_protection: 2,
+            wrap: false,
+            food_lifetime: 30,
+            turn_policy: TurnPolicy::default(),
+        }
+    }
+}
+
+impl Rules {
+    pub fn with_half_extent(mut self, half_extent: IVec3) -> Self {
+        self.half_extent = half_extent;
+        self
+    }
+
+    pub fn with_ouroboros(mut self, ouroboros: bool) -> Self {
+        self.ouroboros = ouroboros;
+        self
+    }
+
+    pub fn with_spawn_protection(mut self, radius: u32) -> Self {
+        self.spawn_protection = radius;
+        self
+    }
+
+    pub fn with_wrap(mut self, wrap: bool) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    pub fn with_food_lifetime(mut self, ticks: u32) -> Self {
+        self.food_lifetime = ticks;
+        self
+    }
+
+    pub fn with_turn_policy(mut self, policy: TurnPolicy) -> Self {
+        self.turn_policy = policy;
+        self
+    }
+}
+
+/// What happened during a single step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TickOutcome {
+    Moved,
+    Ate,
+    Won,
+    /// The head left the arena or ran into the body or a wall, a death in
+    /// the winning tick loses
+    Died,
+}
+
+/// The food on the board and the ticks it has spent in its cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BoardFood {
+    cell: IVec3,
+    kind: FoodKind,
+    age: u32,
+}
+
+/// A run of the game, independent of Bevy's ECS
+///
+/// Plays by the app's rules with a single food on the board. Keys and locks
+/// and segment gravity are left to the app.
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    rules: Rules,
+    rng: StdRng,
+    /// Head first, tail tip last
+    body: VecDeque<IVec3>,
+    direction: Direction,
+    /// Turns requested since the last step, resolved by the rules' policy
+    turns: TurnBuffer,
+    walls: Vec<IVec3>,
+    food: Option<BoardFood>,
+    /// Segments still to grow, one per tick
+    pending_growth: u32,
+    score: u32,
+    tick: u64,
+    /// How the run ended, steps do nothing once it is set
+    ended: Option<TickOutcome>,
+}
+
+impl Simulation {
+    /// Starts a run laid out like the app does, the same `seed` always
+    /// plays out the same way for the same inputs
+    pub fn new(rules: Rules, seed: u64) -> Self {
+        Self::with_walls(rules, seed, [])
+    }
+
+    /// Like [`Simulation::new`] with wall blocks on `walls`, cells outside
+    /// the arena or under the starting snake are skipped like the app does
+    pub fn with_walls(rules: Rules, seed: u64, walls: impl IntoIterator<Item = IVec3>) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let walls: Vec<IVec3> = walls
+            .into_iter()
+            .filter(|cell| {
+                grid_math::is_in_bounds(*cell, rules.half_extent) && !START_CELLS.contains(cell)
+            })
+            .collect();
+
+        let occupied: Vec<IVec3> = START_CELLS.iter().chain(&walls).copied().collect();
+        let food = pick_food_cell(
+            &mut rng,
+            rules.half_extent,
+            &occupied,
+            &START_CELLS,
+            rules.spawn_protection,
+        )
+        .map(|cell| BoardFood {
+            cell,
+            kind: FoodKind::Regular,
+            age: 0,
+        });
+
+        Self {
+            rules,
+            rng,
+            body: VecDeque::from(START_CELLS),
+            direction: Direction::Up,
+            turns: TurnBuffer::default(),
+            walls,
+            food,
+            pending_growth: 0,
+            score: 0,
+            tick: 0,
+            ended: None,
+        }
+    }
+
+    /// Requests a turn for the next steps, returns whether the rules' turn
+    /// policy kept it
+    pub fn turn(&mut self, direction: Direction) -> bool {
+        self.turns
+            .request(self.rules.turn_policy, self.direction, direction)
+    }
+
+    /// Runs one gameplay tick, requesting a turn towards `input` first if
+    /// given
+    ///
+    /// Like in the app, an input reversing the snake into its neck is
+    /// ignored, however short the snake is. Once the run is won or lost
+    /// the board stays as it is and the final outcome is returned again.
+    pub fn step(&mut self, input: Option<Direction>) -> TickOutcome {
+        if let Some(outcome) = self.ended {
+            return outcome;
+        }
+
+        if let Some(direction) = input {
+            self.turn(direction);
+        }
+        if let Some(direction) = self.turns.take(self.direction) {
+            self.direction = direction;
+        }
+        self.tick += 1;
+
+        let next = grid_math::saturating_step(self.head(), IVec3::from(self.direction));
+        let head = if self.rules.wrap {
+            grid_math::wrap_to_bounds(next, self.rules.half_extent)
+        } else {
+            next
+        };
+        self.body.push_front(head);
+        let vacated = self.body.pop_back().unwrap_or(head);
+
+        let eaten = self
+            .food
+            .filter(|food| food.cell == head)
+            .map(|food| food.kind);
+        if let Some(kind) = eaten {
+            self.food = None;
+            self.score += kind.points();
+            self.pending_growth += kind.growth();
+        }
+
+        // checked before growing, the new tail tip is not there yet in the app
+        let won =
+            self.rules.ouroboros && self.body.len() >= OUROBOROS_MIN_LENGTH && head == vacated;
+        let died = !grid_math::is_in_bounds(head, self.rules.half_extent)
+            || self.body.iter().skip(1).any(|cell| *cell == head)
+            || self.walls.contains(&head);
+
+        // only one cell was vacated, the rest of the growth carries over
+        if self.pending_growth > 0 {
+            self.pending_growth -= 1;
+            self.body.push_back(vacated);
+        }
+
+        self.relocate_stale_food();
+        if self.food.is_none() {
+            self.food = self.spawn_food();
+        }
+
+        let outcome = if died {
+            TickOutcome::Died
+        } else if won {
+            TickOutcome::Won
+        } else if eaten.is_some() {
+            TickOutcome::Ate
+        } else {
+            TickOutcome::Moved
+        };
+        if matches!(outcome, TickOutcome::Died | TickOutcome::Won) {
+            self.ended = Some(outcome);
+        }
+        outcome
+    }
+
+    /// Cells food cannot go to, `food` included
+    fn occupied(&self) -> Vec<IVec3> {
+        self.body
+            .iter()
+            .chain(&self.walls)
+            .copied()
+            .chain(self.food.map(|food| food.cell))
+            .collect()
+    }
+
+    /// Ages the food and moves it to a free cell once it reached its
+    /// lifetime, like the app's food timer
+    fn relocate_stale_food(&mut self) {
+        let lifetime = self.rules.food_lifetime;
+        let occupied = self.occupied();
+        let Some(food) = &mut self.food else {
+            return;
+        };
+
+        food.age += 1;
+        if lifetime == 0 || food.age < lifetime {
+            return;
+        }
+        if let Some(cell) = pick_food_cell(&mut self.rng, self.rules.half_extent, &occupied, &[], 0)
+        {
+            food.cell = cell;
+            food.age = 0;
+        }
+    }
+
+    /// A free cell of the arena and the kind of food put there, `None` once
+    /// the snake fills it
+    ///
+    /// Draws from the rng in the app's order: the cell, then slow motion,
+    /// then golden.
+    fn spawn_food(&mut self) -> Option<BoardFood> {
+        let occupied = self.occupied();
+        let cell = pick_food_cell(&mut self.rng, self.rules.half_extent, &occupied, &[], 0)?;
+        let kind = if self.rng.gen_bool(SLOW_MOTION_CHANCE) {
+            FoodKind::SlowMotion
+        } else if self.rng.gen_bool(GOLDEN_CHANCE) {
+            FoodKind::Golden
+        } else {
+            FoodKind::Regular
+        };
+        Some(BoardFood { cell, kind, age: 0 })
+    }
+
+    pub fn rules(&self) -> Rules {
+        self.rules
+    }
+
+    pub fn head(&self) -> IVec3 {
+        self.body[0]
+    }
+
+    /// Every cell of the snake, head first
+    pub fn body(&self) -> impl Iterator<Item = IVec3> + '_ {
+        self.body.iter().copied()
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Wall blocks of the arena
+    pub fn walls(&self) -> &[IVec3] {
+        &self.walls
+    }
+
+    pub fn food(&self) -> Option<IVec3> {
+        self.food.map(|food| food.cell)
+    }
+
+    pub fn food_kind(&self) -> Option<FoodKind> {
+        self.food.map(|food| food.kind)
+    }
+
+    /// Segments the snake still has to grow
+    pub fn pending_growth(&self) -> u32 {
+        self.pending_growth
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    /// Steps run since the start
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// How the run ended, `None` while it goes on
+    pub fn ended(&self) -> Option<TickOutcome> {
+        self.ended
+    }
+
+    /// Hash of the board, equal for runs that ended up in the same state
+    /// and to the app's board hash after the same tick, see [`board_hash`]
+    pub fn state_hash(&self) -> u64 {
+        board_hash(
+            Some((self.head(), self.direction)),
+            self.body().skip(1),
+            self.food(),
+            self.score,
+            self.tick,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn board_hash_ignores_cell_order() {
+        let head = Some((IVec3::ZERO, Direction::Up));
+        let body = [IVec3::NEG_Y, IVec3::new(0, -2, 0), IVec3::new(1, -2, 0)];
+        let food = [IVec3::X, IVec3::Z];
+
+        let hash = board_hash(head, body, food, 3, 10);
+        let reversed = board_hash(head, body.into_iter().rev(), food.into_iter().rev(), 3, 10);
+        assert_eq!(hash, reversed);
+    }
+
+    #[test]
+    fn board_hash_tells_body_from_food() {
+        let head = Some((IVec3::ZERO, Direction::Up));
+        let body_only = board_hash(head, [IVec3::NEG_Y, IVec3::X], [], 0, 1);
+        let with_food = board_hash(head, [IVec3::NEG_Y], [IVec3::X], 0, 1);
+        assert_ne!(body_only, with_food);
+
+        let turned = board_hash(
+            Some((IVec3::ZERO, Direction::Right)),
+            [IVec3::NEG_Y],
+            [IVec3::X],
+            0,
+            1,
+        );
+        assert_ne!(with_food, turned);
+    }
+
+    #[test]
+    fn turns_follow_the_turn_policy() {
+        let heading_after = |policy| {
+            let mut sim = Simulation::new(Rules::default().with_turn_policy(policy), 7);
+            sim.turn(Direction::Right);
+            sim.turn(Direction::Forward);
+            sim.step(None);
+            let first = sim.direction();
+            sim.step(None);
+            (first, sim.direction())
+        };
+
+        assert_eq!(
+            heading_after(TurnPolicy::LastWins),
+            (Direction::Forward, Direction::Forward)
+        );
+        assert_eq!(
+            heading_after(TurnPolicy::FirstWins),
+            (Direction::Right, Direction::Right)
+        );
+        assert_eq!(
+            heading_after(TurnPolicy::Queue),
+            (Direction::Right, Direction::Forward)
+        );
+    }
+
+    #[test]
+    fn state_hash_is_the_board_hash() {
+        let mut sim = Simulation::new(Rules::default(), 7);
+        sim.step(Some(Direction::Right));
+
+        let body: Vec<IVec3> = sim.body().skip(1).collect();
+        let expected = board_hash(
+            Some((sim.head(), sim.direction())),
+            body,
+            sim.food(),
+            sim.score(),
+            sim.tick(),
+        );
+        assert_eq!(sim.state_hash(), expected);
+    }
+
+    #[test]
+    fn food_keeps_clear_of_the_protection_zone() {
+        let half_extent = IVec3::splat(3);
+        let protected = [IVec3::ZERO, IVec3::NEG_Y];
+
+        for seed in 0..200 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let cell = pick_food_cell(&mut rng, half_extent, &protected, &protected, 2).unwrap();
+            assert!(
+                protected
+                    .iter()
+                    .all(|p| grid_math::chebyshev_distance(cell, *p) > 2),
+                "seed {seed} picked {cell} inside the protection zone"
+            );
+        }
+    }
+
+    #[test]
+    fn a_zone_covering_the_arena_leaves_nothing_to_pick() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let half_extent = IVec3::splat(2);
+        assert_eq!(
+            pick_food_cell(&mut rng, half_extent, &[], &[IVec3::ZERO], 2),
+            None
+        );
+        assert!(pick_food_cell(&mut rng, half_extent, &[], &[IVec3::ZERO], 1).is_some());
+    }
+
+    /// Cells of a snake winding through the arena row by row, the way a long
+    /// snake fills it, leaving the last `free` cells empty
+    fn winding_snake(half_extent: IVec3, free: usize) -> Vec<IVec3> {
+        let mut cells = Vec::new();
+        for y in -half_extent.y..=half_extent.y {
+            let mut rows: Vec<i32> = (-half_extent.z..=half_extent.z).collect();
+            // every other layer walks its rows back, so it starts above the
+            // row the layer below ended on
+            if (y + half_extent.y) % 2 == 1 {
+                rows.reverse();
+            }
+            for z in rows {
+                let row: Vec<_> = (-half_extent.x..=half_extent.x)
+                    .map(|x| IVec3::new(x, y, z))
+                    .collect();
+                // alternate the direction so consecutive cells stay adjacent
+                if cells.len() / row.len() % 2 == 0 {
+                    cells.extend(row);
+                } else {
+                    cells.extend(row.into_iter().rev());
+                }
+            }
+        }
+        cells.truncate(cells.len() - free);
+        cells
+    }
+
+    #[test]
+    fn winding_snakes_are_connected() {
+        let snake = winding_snake(IVec3::splat(2), 0);
+        assert_eq!(snake.len(), 125);
+        for pair in snake.windows(2) {
+            assert_eq!(grid_math::manhattan_distance(pair[0], pair[1]), 1);
+        }
+    }
+
+    #[test]
+    fn food_never_lands_on_a_long_snake() {
+        let half_extent = IVec3::splat(2);
+        let snake = winding_snake(half_extent, 6);
+        let free: Vec<IVec3> = winding_snake(half_extent, 0)
+            .into_iter()
+            .filter(|cell| !snake.contains(cell))
+            .collect();
+
+        let mut rng = StdRng::seed_from_u64(258);
+        let mut picked = Vec::new();
+        for _ in 0..1000 {
+            let cell = pick_food_cell(&mut rng, half_extent, &snake, &[], 0).unwrap();
+            assert!(!snake.contains(&cell), "picked {cell} under the snake");
+            if !picked.contains(&cell) {
+                picked.push(cell);
+            }
+        }
+        // every free cell is reachable, the pick is not stuck on one
+        assert_eq!(picked.len(), free.len());
+    }
+
+    #[test]
+    fn a_snake_filling_the_arena_leaves_nothing_to_pick() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let half_extent = IVec3::splat(2);
+        let snake = winding_snake(half_extent, 0);
+        assert_eq!(pick_food_cell(&mut rng, half_extent, &snake, &[], 0), None);
+    }
+}
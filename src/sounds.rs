@@ -0,0 +1,182 @@
+//! Eat sounds, a short tone per kind of food, and a falling tune on death
+//!
+//! There are no audio assets, the tones are synthesized like the food ping
+//! and the milestone fanfare. Regular food eaten in quick succession builds
+//! a [`Combo`] that plays its tone a little faster and higher each time.
+
+use std::time::Duration;
+
+use bevy::{
+    audio::{Pitch, PitchBundle},
+    prelude::*,
+};
+
+use crate::{DeathEvent, EatEvent, FoodKind, StartRun, TickTimer};
+
+/// Descending notes played when the snake dies, in Hz
+const DEATH_NOTES: [f32; 3] = [392.00, 311.13, 196.00];
+
+/// Length of each note of the death sound
+const DEATH_NOTE: Duration = Duration::from_millis(180);
+
+/// Ticks the next food has to be eaten within to keep the combo going
+const COMBO_WINDOW_TICKS: u64 = 12;
+
+/// Playback rate gained per food of the combo after the first
+const COMBO_RATE_STEP: f32 = 0.06;
+
+/// Highest playback rate a combo can reach, a fifth above the tone
+const MAX_COMBO_RATE: f32 = 1.5;
+
+/// Food eaten in quick succession during the current run
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Combo {
+    /// Food in the combo, 0 before anything is eaten
+    pub count: u32,
+    /// Tick the last food was eaten on
+    last_tick: u64,
+}
+
+impl Combo {
+    /// Counts a food eaten on `tick`, starting over if the last one is too
+    /// far back
+    fn eat(&mut self, tick: u64) {
+        let in_window =
+            self.count > 0 && tick >= self.last_tick && tick - self.last_tick <= COMBO_WINDOW_TICKS;
+        self.count = if in_window { self.count + 1 } else { 1 };
+        self.last_tick = tick;
+    }
+}
+
+/// Playback rate of the regular eat tone for a combo of `combo` food,
+/// rising by a step per food and clamped to [`MAX_COMBO_RATE`]
+pub fn combo_rate(combo: u32) -> f32 {
+    let steps = combo.saturating_sub(1) as f32;
+    (1.0 + COMBO_RATE_STEP * steps).min(MAX_COMBO_RATE)
+}
+
+/// Frequency in Hz and length of the tone played when a kind of food is eaten
+fn eat_tone(kind: FoodKind) -> (f32, Duration) {
+    match kind {
+        FoodKind::Regular => (659.25, Duration::from_millis(60)),
+        FoodKind::SlowMotion => (329.63, Duration::from_millis(200)),
+        FoodKind::Key => (987.77, Duration::from_millis(90)),
+        FoodKind::Unlocked => (1318.51, Duration::from_millis(140)),
+        FoodKind::Golden => (783.99, Duration::from_millis(250)),
+    }
+}
+
+pub struct SoundsPlugin;
+
+impl Plugin for SoundsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Combo>()
+            .add_systems(StartRun, reset_combo)
+            .add_systems(Update, (play_eat_sounds, play_death_sound));
+    }
+}
+
+fn reset_combo(mut combo: ResMut<Combo>) {
+    *combo = Combo::default();
+}
+
+fn play_eat_sounds(
+    mut commands: Commands,
+    mut eat_reader: EventReader<EatEvent>,
+    mut pitches: ResMut<Assets<Pitch>>,
+    mut combo: ResMut<Combo>,
+    tick: Res<TickTimer>,
+) {
+    for EatEvent { kind } in eat_reader.read() {
+        combo.eat(tick.count);
+        let speed = match kind {
+            FoodKind::Regular => combo_rate(combo.count),
+            _ => 1.0,
+        };
+
+        let (frequency, duration) = eat_tone(*kind);
+        commands.spawn((
+            PitchBundle {
+                source: pitches.add(Pitch::new(frequency, duration)),
+                settings: PlaybackSettings {
+                    speed,
+                    ..PlaybackSettings::DESPAWN
+                },
+            },
+            Name::new("Eat Sound"),
+        ));
+    }
+}
+
+/// Plays the death notes one after the other, each waiting out the ones
+/// before it
+fn play_death_sound(
+    time: Res<Time<Real>>,
+    mut death_reader: EventReader<DeathEvent>,
+    mut pitches: ResMut<Assets<Pitch>>,
+    mut queued: Local<Vec<(Duration, f32)>>,
+    mut commands: Commands,
+) {
+    let now = time.elapsed();
+    if death_reader.read().next().is_some() {
+        death_reader.clear();
+        queued.clear();
+        queued.extend(
+            DEATH_NOTES
+                .iter()
+                .enumerate()
+                .map(|(i, frequency)| (now + DEATH_NOTE * i as u32, *frequency)),
+        );
+    }
+
+    queued.retain(|(at, frequency)| {
+        if *at > now {
+            return true;
+        }
+        commands.spawn((
+            PitchBundle {
+                source: pitches.add(Pitch::new(*frequency, DEATH_NOTE)),
+                settings: PlaybackSettings::DESPAWN,
+            },
+            Name::new("Death Sound"),
+        ));
+        false
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combo_rate_rises_then_levels_off() {
+        assert_eq!(combo_rate(0), 1.0);
+        assert_eq!(combo_rate(1), 1.0);
+        assert!(combo_rate(2) > combo_rate(1));
+        assert!(combo_rate(5) > combo_rate(2));
+        assert_eq!(combo_rate(1000), MAX_COMBO_RATE);
+        assert_eq!(combo_rate(u32::MAX), MAX_COMBO_RATE);
+    }
+
+    #[test]
+    fn combo_rate_never_drops() {
+        for combo in 0..100 {
+            assert!(combo_rate(combo + 1) >= combo_rate(combo));
+        }
+    }
+
+    #[test]
+    fn combo_breaks_outside_the_window() {
+        let mut combo = Combo::default();
+        combo.eat(10);
+        combo.eat(10 + COMBO_WINDOW_TICKS);
+        assert_eq!(combo.count, 2);
+
+        combo.eat(11 + 2 * COMBO_WINDOW_TICKS);
+        assert_eq!(combo.count, 1);
+
+        // the tick count starts over with a new run
+        combo.eat(3);
+        assert_eq!(combo.count, 1);
+    }
+}
@@ -0,0 +1,192 @@
+//! Tooltip naming the food or wall under the cursor, only during a run so
+//! it never covers a menu
+
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    hud::UiAssets,
+    locks::{KeyFor, Locked, Unlocked, UNLOCKED_POINTS},
+    picking::HoveredCell,
+    walls::Wall,
+    AppState, Food, FoodKind, GoldenFood, Position, SlowMotionFood, GOLDEN_GROWTH, GOLDEN_POINTS,
+    SLOW_MOTION_TICKS,
+};
+
+/// Seconds the cursor rests on a cell before its tooltip shows, so moving
+/// across cell boundaries does not flicker
+const HOVER_SECONDS: f32 = 0.15;
+
+/// Offset from the cursor, in logical pixels
+const CURSOR_OFFSET: Vec2 = Vec2::new(16.0, 16.0);
+
+/// Tag for the tooltip text
+#[derive(Component, Debug, Default)]
+struct Tooltip;
+
+/// What the hovered cell holds, as far as the tooltip tells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CellContent {
+    Wall,
+    /// A food caged until its key is eaten
+    Locked,
+    /// A key, with the cell of the food it unlocks
+    Key(IVec3),
+    Food(FoodKind),
+}
+
+pub struct TooltipPlugin;
+
+impl Plugin for TooltipPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostStartup, setup_tooltip).add_systems(
+            Update,
+            show_tooltip.after(crate::picking::update_hovered_cell),
+        );
+    }
+}
+
+fn setup_tooltip(mut commands: Commands, ui_assets: Res<UiAssets>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: ui_assets.font.clone(),
+                font_size: 16.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            padding: UiRect::all(Val::Px(4.0)),
+            ..default()
+        })
+        .with_background_color(Color::rgba(0.0, 0.0, 0.0, 0.7)),
+        Tooltip,
+        Name::new("Tooltip"),
+    ));
+}
+
+/// Describes what the hovered cell holds
+fn describe(content: CellContent) -> String {
+    match content {
+        CellContent::Wall => "Wall, running into it ends the run".to_string(),
+        CellContent::Locked => "Caged food, eat its key first".to_string(),
+        CellContent::Key(lock) => format!("Key, unlocks the food at {lock}"),
+        CellContent::Food(FoodKind::Unlocked) => {
+            format!("Unlocked food, +{UNLOCKED_POINTS} points")
+        }
+        CellContent::Food(FoodKind::SlowMotion) => {
+            format!("Slow motion food, slows the game for {SLOW_MOTION_TICKS} ticks")
+        }
+        CellContent::Food(FoodKind::Golden) => {
+            format!("Golden food, +{GOLDEN_GROWTH} segments and +{GOLDEN_POINTS} points")
+        }
+        CellContent::Food(FoodKind::Regular | FoodKind::Key) => "Food, +1 segment".to_string(),
+    }
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn show_tooltip(
+    time: Res<Time>,
+    state: Res<State<AppState>>,
+    hovered: Res<HoveredCell>,
+    primary_window_q: Query<&Window, With<PrimaryWindow>>,
+    food: Query<
+        (
+            &Position,
+            Option<&KeyFor>,
+            Option<&Locked>,
+            Option<&Unlocked>,
+            Option<&SlowMotionFood>,
+            Option<&GoldenFood>,
+        ),
+        With<Food>,
+    >,
+    lock_cells: Query<&Position, With<Locked>>,
+    walls: Query<&Position, With<Wall>>,
+    mut tooltip: Query<(&mut Text, &mut Style, &mut Visibility), With<Tooltip>>,
+    mut resting: Local<Timer>,
+) {
+    let Ok((mut text, mut style, mut visibility)) = tooltip.get_single_mut() else {
+        return;
+    };
+
+    if hovered.is_changed() {
+        *resting = Timer::from_seconds(HOVER_SECONDS, TimerMode::Once);
+    }
+    resting.tick(time.delta());
+
+    let cursor = primary_window_q
+        .get_single()
+        .ok()
+        .and_then(Window::cursor_position);
+    let playing = *state.get() == AppState::Playing;
+    let content = hovered.0.filter(|_| playing).and_then(|cell| {
+        if walls.iter().any(|pos| pos.0 == cell) {
+            return Some(CellContent::Wall);
+        }
+
+        let (_, key, locked, unlocked, slow_motion, golden) =
+            food.iter().find(|(pos, ..)| pos.0 == cell)?;
+        let lock = key.and_then(|KeyFor(target)| lock_cells.get(*target).ok());
+        Some(if locked.is_some() {
+            CellContent::Locked
+        } else if let Some(lock) = lock {
+            CellContent::Key(lock.0)
+        } else if unlocked.is_some() {
+            CellContent::Food(FoodKind::Unlocked)
+        } else if slow_motion.is_some() {
+            CellContent::Food(FoodKind::SlowMotion)
+        } else if golden.is_some() {
+            CellContent::Food(FoodKind::Golden)
+        } else {
+            CellContent::Food(FoodKind::Regular)
+        })
+    });
+
+    let (Some(cursor), Some(content)) = (cursor, content) else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+    if !resting.finished() {
+        return;
+    }
+
+    text.sections[0].value = describe(content);
+    style.left = Val::Px(cursor.x + CURSOR_OFFSET.x);
+    style.top = Val::Px(cursor.y + CURSOR_OFFSET.y);
+    *visibility = Visibility::Inherited;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn descriptions_name_what_is_hovered() {
+        assert_eq!(
+            describe(CellContent::Wall),
+            "Wall, running into it ends the run"
+        );
+        assert_eq!(
+            describe(CellContent::Locked),
+            "Caged food, eat its key first"
+        );
+        assert_eq!(
+            describe(CellContent::Key(IVec3::new(1, -2, 3))),
+            format!("Key, unlocks the food at {}", IVec3::new(1, -2, 3))
+        );
+        assert_eq!(
+            describe(CellContent::Food(FoodKind::Regular)),
+            "Food, +1 segment"
+        );
+        assert_eq!(
+            describe(CellContent::Food(FoodKind::Golden)),
+            format!("Golden food, +{GOLDEN_GROWTH} segments and +{GOLDEN_POINTS} points")
+        );
+        assert!(
+            describe(CellContent::Food(FoodKind::Unlocked)).contains(&UNLOCKED_POINTS.to_string())
+        );
+        assert!(describe(CellContent::Food(FoodKind::SlowMotion)).starts_with("Slow motion"));
+    }
+}
@@ -0,0 +1,179 @@
+//! Turns requested between two ticks and which of them the next tick takes
+//!
+//! Inputs only reach the snake through a [`TurnBuffer`], so every input
+//! source follows the same [`TurnPolicy`]. What comes out of the buffer is
+//! the direction a tick actually moved in, a record of those replays the
+//! same way under any policy.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::Direction;
+
+/// Turns the `Queue` policy keeps ahead of the snake, enough for a quick
+/// double turn without letting a dozen inputs be buffered
+pub const MAX_QUEUED_TURNS: usize = 2;
+
+/// How turns requested between two ticks are resolved, picked per run by
+/// [`Rules::turn_policy`](crate::Rules::turn_policy)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum TurnPolicy {
+    /// The latest turn replaces any earlier one
+    LastWins,
+    /// The first turn locks in until the tick takes it
+    FirstWins,
+    /// Every turn is kept and taken one per tick, so quick corners are not lost
+    #[default]
+    Queue,
+}
+
+/// Turns waiting for the next ticks
+#[derive(Resource, Debug, Default, Clone, PartialEq, Eq)]
+pub struct TurnBuffer {
+    pending: VecDeque<Direction>,
+}
+
+impl TurnBuffer {
+    /// Direction the snake will be heading once the buffer has drained
+    pub fn latest(&self, heading: Direction) -> Direction {
+        self.pending.back().copied().unwrap_or(heading)
+    }
+
+    /// Direction the next tick will move in
+    pub fn next(&self, heading: Direction) -> Direction {
+        self.pending.front().copied().unwrap_or(heading)
+    }
+
+    /// Offers a turn while the snake is `heading` somewhere, returns whether
+    /// it was kept
+    ///
+    /// A turn that would not change the direction is not a valid input, so
+    /// it can neither lock in nor fill the queue. Reversing into the neck is
+    /// silently ignored. Queued turns are checked against the turn queued
+    /// before them, and again once they are taken.
+    pub fn request(&mut self, policy: TurnPolicy, heading: Direction, turn: Direction) -> bool {
+        if policy != TurnPolicy::Queue && turn.reverses(heading) {
+            return false;
+        }
+
+        match policy {
+            TurnPolicy::LastWins => {
+                self.pending.clear();
+                if turn != heading {
+                    self.pending.push_back(turn);
+                }
+                true
+            }
+            TurnPolicy::FirstWins => {
+                if !self.pending.is_empty() || turn == heading {
+                    return false;
+                }
+                self.pending.push_back(turn);
+                true
+            }
+            TurnPolicy::Queue => {
+                let previous = self.latest(heading);
+                if self.pending.len() >= MAX_QUEUED_TURNS
+                    || turn == previous
+                    || turn.reverses(previous)
+                {
+                    return false;
+                }
+                self.pending.push_back(turn);
+                true
+            }
+        }
+    }
+
+    /// Takes the turn for the tick being run, `None` keeps the heading
+    ///
+    /// A turn reversing the direction the snake is `heading` when the tick
+    /// fires is dropped.
+    pub fn take(&mut self, heading: Direction) -> Option<Direction> {
+        self.pending
+            .pop_front()
+            .filter(|turn| !turn.reverses(heading))
+    }
+
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Turns requested between two ticks while heading up
+    const INPUTS: [Direction; 4] = [
+        Direction::Right,
+        Direction::Down,
+        Direction::Forward,
+        Direction::Backward,
+    ];
+
+    /// Feeds [`INPUTS`] to a buffer, then returns the direction each of
+    /// the next three ticks moves in
+    fn resolve(policy: TurnPolicy) -> Vec<Direction> {
+        let mut buffer = TurnBuffer::default();
+        let mut heading = Direction::Up;
+        for turn in INPUTS {
+            buffer.request(policy, heading, turn);
+        }
+
+        (0..3)
+            .map(|_| {
+                heading = buffer.take(heading).unwrap_or(heading);
+                heading
+            })
+            .collect()
+    }
+
+    #[test]
+    fn last_wins_keeps_the_latest_valid_turn() {
+        assert_eq!(
+            resolve(TurnPolicy::LastWins),
+            [
+                Direction::Backward,
+                Direction::Backward,
+                Direction::Backward
+            ]
+        );
+
+        let mut buffer = TurnBuffer::default();
+        assert!(buffer.request(TurnPolicy::LastWins, Direction::Up, Direction::Right));
+        // going straight again cancels the turn
+        assert!(buffer.request(TurnPolicy::LastWins, Direction::Up, Direction::Up));
+        assert_eq!(buffer.take(Direction::Up), None);
+    }
+
+    #[test]
+    fn first_wins_locks_in_the_first_valid_turn() {
+        assert_eq!(
+            resolve(TurnPolicy::FirstWins),
+            [Direction::Right, Direction::Right, Direction::Right]
+        );
+
+        let mut buffer = TurnBuffer::default();
+        // a reversal or no turn at all is not valid, so it cannot lock in
+        assert!(!buffer.request(TurnPolicy::FirstWins, Direction::Up, Direction::Down));
+        assert!(!buffer.request(TurnPolicy::FirstWins, Direction::Up, Direction::Up));
+        assert!(buffer.request(TurnPolicy::FirstWins, Direction::Up, Direction::Left));
+    }
+
+    #[test]
+    fn queue_takes_one_turn_per_tick() {
+        // down is no reversal once the snake heads right
+        assert_eq!(
+            resolve(TurnPolicy::Queue),
+            [Direction::Right, Direction::Down, Direction::Down]
+        );
+
+        let mut buffer = TurnBuffer::default();
+        assert!(buffer.request(TurnPolicy::Queue, Direction::Up, Direction::Left));
+        assert!(buffer.request(TurnPolicy::Queue, Direction::Up, Direction::Down));
+        assert!(!buffer.request(TurnPolicy::Queue, Direction::Up, Direction::Forward));
+        assert_eq!(buffer.latest(Direction::Up), Direction::Down);
+    }
+}
@@ -0,0 +1,143 @@
+//! World invariants checked after every tick, for development and soak runs
+//!
+//! Enabled with `--validate`, in debug and release builds alike. The plugin
+//! is not added otherwise, so runs without the flag pay nothing for it. A
+//! violation panics with every broken invariant of the tick listed.
+//!
+//! Every entity with a [`Position`] is a snake segment, a food or a wall,
+//! and food never sits on a wall.
+//!
+//! The snake may overlap itself (segments grown by the console stack on the
+//! tail tip) and gravity pulls segments apart, so distinct and contiguous
+//! body cells are not checked. On the tick the snake dies its head may be
+//! outside the arena or inside its body.
+
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use crate::{
+    grid_math,
+    locks::{KeyFor, Locked},
+    walls::Wall,
+    DeathEvent, Food, GridBounds, LastSnakeSegment, Position, SnakeHead, SnakeSegment, TickTimer,
+};
+
+/// Whether the invariants are checked, from the command line
+pub fn enabled(mut args: impl Iterator<Item = String>) -> bool {
+    args.any(|arg| arg == "--validate")
+}
+
+pub struct ValidatePlugin;
+
+impl Plugin for ValidatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, validate_world.in_set(crate::TickSet::Record));
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn validate_world(
+    tick: Res<TickTimer>,
+    bounds: Res<GridBounds>,
+    positioned: Query<(
+        Entity,
+        &Position,
+        Option<&SnakeSegment>,
+        Option<&Food>,
+        Option<&SnakeHead>,
+        Option<&LastSnakeSegment>,
+        Option<&Wall>,
+    )>,
+    keys: Query<(Entity, &KeyFor)>,
+    locked: Query<Entity, With<Locked>>,
+    mut death_reader: EventReader<DeathEvent>,
+) {
+    let dying = death_reader.read().count() > 0;
+    let mut violations = Vec::new();
+    let mut heads = 0;
+    let mut tails = 0;
+    let mut food_cells = HashSet::new();
+    let mut wall_cells = HashSet::new();
+
+    for (ent, pos, segment, food, head, tail, wall) in positioned.iter() {
+        let may_leave = dying && head.is_some();
+        if !may_leave && !grid_math::is_in_bounds(pos.0, bounds.half_extent) {
+            violations.push(format!("{ent:?} at {} is outside the arena", pos.0));
+        }
+        let kinds = [segment.is_some(), food.is_some(), wall.is_some()];
+        if kinds.into_iter().filter(|kind| *kind).count() != 1 {
+            violations.push(format!(
+                "{ent:?} at {} must be exactly one of a snake segment, a food or a wall",
+                pos.0
+            ));
+        }
+        if wall.is_some() && !wall_cells.insert(pos.0) {
+            violations.push(format!("more than one wall at {}", pos.0));
+        }
+        if food.is_some() && !food_cells.insert(pos.0) {
+            violations.push(format!("more than one food at {}", pos.0));
+        }
+        if (head.is_some() || tail.is_some()) && segment.is_none() {
+            violations.push(format!("{ent:?} is a head or tail but not a segment"));
+        }
+        if head.is_some() && tail.is_some() {
+            violations.push(format!("{ent:?} is both the head and the tail"));
+        }
+        heads += usize::from(head.is_some());
+        tails += usize::from(tail.is_some());
+    }
+
+    for cell in food_cells.intersection(&wall_cells) {
+        violations.push(format!("food inside the wall at {cell}"));
+    }
+
+    if heads != 1 {
+        violations.push(format!("{heads} snake heads"));
+    }
+    if tails != 1 {
+        violations.push(format!("{tails} last snake segments"));
+    }
+
+    for (key, KeyFor(target)) in keys.iter() {
+        if !locked.contains(*target) {
+            violations.push(format!("key {key:?} opens {target:?}, which is not locked"));
+        }
+    }
+    for lock in locked.iter() {
+        let count = keys
+            .iter()
+            .filter(|(_, KeyFor(target))| *target == lock)
+            .count();
+        if count != 1 {
+            violations.push(format!("locked food {lock:?} has {count} keys"));
+        }
+    }
+
+    if !violations.is_empty() {
+        panic!(
+            "World invariants broken after tick {}:\n  {}",
+            tick.count,
+            violations.join("\n  ")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn only_the_flag_enables_validation() {
+        assert!(!enabled(args(&[])));
+        assert!(!enabled(args(&["--seed", "3"])));
+        assert!(enabled(args(&["--seed", "3", "--validate"])));
+    }
+}
@@ -0,0 +1,211 @@
+//! Solid blocks inside the arena the snake has to steer around
+//!
+//! The cells come from [`WallLayout`], read from the file given with
+//! `--walls <path>` and empty without it, and are laid out once on startup.
+//! The file lists a cell per line as `x y z`, blank lines and lines starting
+//! with `#` are skipped. Walls outlive runs, restarting only clears the
+//! snake and the food. Running into one ends the run like leaving the arena
+//! does, and food never spawns on one.
+
+use std::{fs, path::PathBuf};
+
+use bevy::prelude::*;
+
+use crate::{
+    DeathEvent, DeathReason, GridBounds, GridConfig, Position, SnakeAssets, SnakeHead, TickTimer,
+};
+
+/// Tag for a wall block
+#[derive(Component, Debug, Default)]
+pub struct Wall;
+
+/// Components making up a wall block
+#[derive(Bundle, Default)]
+pub struct WallBundle {
+    pub wall: Wall,
+    pub position: Position,
+    pub pbr: PbrBundle,
+}
+
+/// Cells walls are laid out on at startup
+#[derive(Resource, Debug, Default, Clone, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub struct WallLayout(pub Vec<IVec3>);
+
+impl WallLayout {
+    /// Read from the file named by `--walls`, empty without the flag or when
+    /// the file cannot be used
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let Some(path) = walls_path(args) else {
+            return Self::default();
+        };
+
+        let layout = fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|contents| parse_wall_layout(&contents));
+        match layout {
+            Ok(cells) => {
+                info!(target: "rsnake3d::walls", count = cells.len(), "Loaded {}", path.display());
+                Self(cells)
+            }
+            Err(err) => {
+                error!(target: "rsnake3d::walls", "Could not load {}: {err}", path.display());
+                Self::default()
+            }
+        }
+    }
+}
+
+/// Reads `--walls <path>` from the process arguments
+fn walls_path(args: impl IntoIterator<Item = String>) -> Option<PathBuf> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if let Some(path) = arg.strip_prefix("--walls=") {
+            return Some(PathBuf::from(path));
+        }
+        if arg == "--walls" {
+            return args.next().map(PathBuf::from);
+        }
+    }
+    None
+}
+
+/// Cells listed by a wall layout file, see the module docs for the format
+pub fn parse_wall_layout(contents: &str) -> Result<Vec<IVec3>, String> {
+    let mut cells = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let coords: Vec<i32> = line
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map_err(|err| format!("line {}: {err}", index + 1))?;
+        let [x, y, z] = coords[..] else {
+            return Err(format!("line {}: expected x y z", index + 1));
+        };
+        cells.push(IVec3::new(x, y, z));
+    }
+    Ok(cells)
+}
+
+pub struct WallsPlugin;
+
+impl Plugin for WallsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<WallLayout>()
+            .init_resource::<WallLayout>()
+            // PostStartup so the meshes loaded on startup are in place
+            .add_systems(PostStartup, spawn_walls)
+            .add_systems(
+                FixedUpdate,
+                check_wall_collision
+                    .after(crate::snake_movement)
+                    .in_set(crate::TickSet::Simulate),
+            );
+    }
+}
+
+/// Spawns a wall on every cell of the layout, skipping cells outside the
+/// arena and the ones the snake starts on
+fn spawn_walls(
+    mut commands: Commands,
+    layout: Res<WallLayout>,
+    grid: Res<GridConfig>,
+    bounds: Res<GridBounds>,
+    snake_assets: Res<SnakeAssets>,
+) {
+    for &cell in &layout.0 {
+        if !crate::grid_math::is_in_bounds(cell, bounds.half_extent)
+            || crate::START_CELLS.contains(&cell)
+        {
+            warn!(target: "rsnake3d::spawn_walls", ?cell, "Skipping wall");
+            continue;
+        }
+
+        commands.spawn((
+            WallBundle {
+                position: Position(cell),
+                pbr: PbrBundle {
+                    mesh: snake_assets.wall_mesh.clone(),
+                    material: snake_assets.wall_material.clone(),
+                    transform: grid.cell_transform(cell),
+                    ..default()
+                },
+                ..default()
+            },
+            Name::new("Wall"),
+        ));
+    }
+    debug!(target: "rsnake3d::spawn_walls", count = layout.0.len());
+}
+
+fn check_wall_collision(
+    walls: Query<&Position, With<Wall>>,
+    head: Query<&Position, With<SnakeHead>>,
+    mut death_writer: EventWriter<DeathEvent>,
+    tick: Res<TickTimer>,
+) {
+    let _span = info_span!("check_wall_collision", tick = tick.count).entered();
+
+    let Ok(head_pos) = head.get_single() else {
+        return;
+    };
+
+    if walls.iter().any(|wall| wall.0 == head_pos.0) {
+        debug!(target: "rsnake3d::check_wall_collision", head = ?head_pos);
+        death_writer.send(DeathEvent {
+            reason: DeathReason::HitWall,
+        });
+        debug!(target: "rsnake3d::events", "Sent DeathEvent");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn walls_path_takes_both_forms() {
+        assert_eq!(walls_path(args(&[])), None);
+        assert_eq!(
+            walls_path(args(&["--seed", "1", "--walls", "level.txt"])),
+            Some(PathBuf::from("level.txt"))
+        );
+        assert_eq!(
+            walls_path(args(&["--walls=level.txt"])),
+            Some(PathBuf::from("level.txt"))
+        );
+    }
+
+    #[test]
+    fn layouts_skip_comments_and_blank_lines() {
+        let contents = "# a ring around the start\n1 0 0\n\n  -1 0 0  \n0 0 -3\n";
+        assert_eq!(
+            parse_wall_layout(contents),
+            Ok(vec![IVec3::X, IVec3::NEG_X, IVec3::new(0, 0, -3)])
+        );
+        assert_eq!(parse_wall_layout(""), Ok(vec![]));
+    }
+
+    #[test]
+    fn malformed_layouts_name_the_line() {
+        assert_eq!(
+            parse_wall_layout("1 0 0\n1 0\n"),
+            Err("line 2: expected x y z".to_string())
+        );
+        assert!(parse_wall_layout("1 0 0 0")
+            .unwrap_err()
+            .starts_with("line 1"));
+        assert!(parse_wall_layout("1 a 0")
+            .unwrap_err()
+            .starts_with("line 1"));
+    }
+}
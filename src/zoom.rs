@@ -0,0 +1,225 @@
+//! Camera distance, zoomed with the mouse wheel or following the snake
+//!
+//! With [`AdaptiveZoom`] on, the camera backs away as the snake spreads out
+//! so its whole body stays in view, up to the distance framing the whole
+//! arena. Scrolling takes over for a few seconds before the adaptive
+//! distance resumes.
+
+use bevy::prelude::*;
+
+use crate::{actions::ActionState, GridBounds, GridConfig, MainCamera, Position, SnakeSegment};
+
+/// Closest the camera gets to the grid origin
+const MIN_DISTANCE: f32 = 6.0;
+
+/// Farthest the camera gets from the grid origin
+const MAX_DISTANCE: f32 = 60.0;
+
+/// Distance the camera starts at
+pub const DEFAULT_DISTANCE: f32 = 17.32;
+
+/// Distance factor of one scrolled line
+const SCROLL_FACTOR: f32 = 0.9;
+
+/// Seconds without scrolling before the adaptive distance resumes
+const MANUAL_HOLD_SECONDS: f32 = 3.0;
+
+/// Fraction of the remaining distance covered per second
+const ZOOM_SPEED: f32 = 2.0;
+
+/// Zoom out as the snake grows so its whole body stays visible
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub struct AdaptiveZoom(pub bool);
+
+/// Smallest box, in cells, containing every given cell
+pub fn cell_bounds(cells: impl IntoIterator<Item = IVec3>) -> Option<(IVec3, IVec3)> {
+    cells.into_iter().fold(None, |bounds, cell| match bounds {
+        Some((min, max)) => Some((cell.min(min), cell.max(max))),
+        None => Some((cell, cell)),
+    })
+}
+
+/// Camera distance from `target` keeping the world space box `min..max`
+/// inside a view with the given vertical field of view
+pub fn framing_distance(min: Vec3, max: Vec3, target: Vec3, fov: f32) -> f32 {
+    let corners = [
+        Vec3::new(min.x, min.y, min.z),
+        Vec3::new(max.x, min.y, min.z),
+        Vec3::new(min.x, max.y, min.z),
+        Vec3::new(max.x, max.y, min.z),
+        Vec3::new(min.x, min.y, max.z),
+        Vec3::new(max.x, min.y, max.z),
+        Vec3::new(min.x, max.y, max.z),
+        Vec3::new(max.x, max.y, max.z),
+    ];
+    let radius = corners
+        .iter()
+        .map(|corner| corner.distance(target))
+        .fold(0.0, f32::max);
+    radius / (fov / 2.0).sin()
+}
+
+/// Where the camera distance is heading
+#[derive(Resource, Debug)]
+struct Zoom {
+    target: f32,
+    /// Running while scrolling overrides the adaptive distance
+    manual: Timer,
+}
+
+impl Default for Zoom {
+    fn default() -> Self {
+        let mut manual = Timer::from_seconds(MANUAL_HOLD_SECONDS, TimerMode::Once);
+        manual.tick(manual.duration());
+        Self {
+            target: DEFAULT_DISTANCE,
+            manual,
+        }
+    }
+}
+
+pub struct ZoomPlugin;
+
+impl Plugin for ZoomPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<AdaptiveZoom>()
+            .init_resource::<AdaptiveZoom>()
+            .init_resource::<Zoom>()
+            .add_systems(
+                Update,
+                (scroll_zoom, adaptive_zoom, apply_zoom)
+                    .chain()
+                    .after(crate::position_translation),
+            );
+    }
+}
+
+fn scroll_zoom(time: Res<Time>, actions: Res<ActionState>, mut zoom: ResMut<Zoom>) {
+    zoom.manual.tick(time.delta());
+
+    let lines = actions.zoom();
+    if lines == 0.0 {
+        return;
+    }
+
+    zoom.target = (zoom.target * SCROLL_FACTOR.powf(lines)).clamp(MIN_DISTANCE, MAX_DISTANCE);
+    zoom.manual.reset();
+    debug!(target: "rsnake3d::scroll_zoom", target = zoom.target);
+}
+
+fn adaptive_zoom(
+    settings: Res<AdaptiveZoom>,
+    grid: Res<GridConfig>,
+    bounds: Res<GridBounds>,
+    mut zoom: ResMut<Zoom>,
+    camera: Query<&Projection, With<MainCamera>>,
+    segments: Query<&Position, With<SnakeSegment>>,
+) {
+    if !settings.0 || !zoom.manual.finished() {
+        return;
+    }
+
+    let Ok(Projection::Perspective(perspective)) = camera.get_single() else {
+        return;
+    };
+    let Some((min, max)) = cell_bounds(segments.iter().map(|p| p.0)) else {
+        return;
+    };
+
+    let half_cell = Vec3::splat(grid.cell_size / 2.0);
+    let world_box = |min: IVec3, max: IVec3| {
+        (
+            grid.cell_to_world(min) - half_cell,
+            grid.cell_to_world(max) + half_cell,
+        )
+    };
+
+    let (snake_min, snake_max) = world_box(min, max);
+    let (arena_min, arena_max) = world_box(-bounds.half_extent, bounds.half_extent);
+    let snake = framing_distance(snake_min, snake_max, grid.origin, perspective.fov);
+    let arena = framing_distance(arena_min, arena_max, grid.origin, perspective.fov);
+
+    zoom.target = snake
+        .clamp(DEFAULT_DISTANCE, arena.max(DEFAULT_DISTANCE))
+        .clamp(MIN_DISTANCE, MAX_DISTANCE);
+}
+
+/// Eases the camera along its view axis towards the target distance
+fn apply_zoom(
+    time: Res<Time>,
+    grid: Res<GridConfig>,
+    zoom: Res<Zoom>,
+    mut camera: Query<&mut Transform, With<MainCamera>>,
+) {
+    let Ok(mut transform) = camera.get_single_mut() else {
+        return;
+    };
+
+    let offset = transform.translation - grid.origin;
+    let distance = offset.length();
+    if (distance - zoom.target).abs() < 0.01 {
+        return;
+    }
+
+    let t = (ZOOM_SPEED * time.delta_seconds()).min(1.0);
+    let distance = distance + (zoom.target - distance) * t;
+    transform.translation = grid.origin + offset.normalize_or_zero() * distance;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    #[test]
+    fn cell_bounds_of_known_layouts() {
+        assert_eq!(cell_bounds(std::iter::empty::<IVec3>()), None);
+        assert_eq!(
+            cell_bounds([IVec3::new(2, -1, 3)]),
+            Some((IVec3::new(2, -1, 3), IVec3::new(2, -1, 3)))
+        );
+
+        // a straight snake along x
+        let straight = (-2..=3).map(|x| IVec3::new(x, 0, 0));
+        assert_eq!(
+            cell_bounds(straight),
+            Some((IVec3::new(-2, 0, 0), IVec3::new(3, 0, 0)))
+        );
+
+        // an L coiling over two layers
+        let coiled = [
+            IVec3::new(0, 0, 0),
+            IVec3::new(1, 0, 0),
+            IVec3::new(1, 0, -1),
+            IVec3::new(1, 1, -1),
+            IVec3::new(0, 1, -1),
+        ];
+        assert_eq!(
+            cell_bounds(coiled),
+            Some((IVec3::new(0, 0, -1), IVec3::new(1, 1, 0)))
+        );
+    }
+
+    #[test]
+    fn framing_distance_fits_the_farthest_corner() {
+        // with a 90° field of view the distance is the radius over sin(45°)
+        let distance = framing_distance(Vec3::splat(-1.0), Vec3::splat(1.0), Vec3::ZERO, FRAC_PI_2);
+        let radius = 3f32.sqrt();
+        assert!((distance - radius * 2f32.sqrt()).abs() < 1e-4);
+
+        // an off center target has to back off further
+        let off_center = framing_distance(
+            Vec3::splat(-1.0),
+            Vec3::splat(1.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            FRAC_PI_2,
+        );
+        assert!(off_center > distance);
+
+        // a narrower field of view needs more distance
+        let narrow = framing_distance(Vec3::splat(-1.0), Vec3::splat(1.0), Vec3::ZERO, 0.5);
+        assert!(narrow > distance);
+    }
+}